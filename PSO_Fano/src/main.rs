@@ -1,5 +1,5 @@
-mod lib;
-use lib::classic_pso;
+use optimizer_core::model::Fano;
+use optimizer_core::{classic_pso, Inertia, PsoOptions};
 use std::time::Instant;
 
 fn main() {
@@ -12,16 +12,22 @@ fn main() {
     let position_min: Vec<f64> = vec![285.0, 1000.0,0.0,0.0];
 
     let start = Instant::now();
-    let (loss, best_position, mean, stdvar) = classic_pso(
-        position_max,
-        position_min,
-        x,
-        y,
+    let ((loss, best_position, mean, stdvar), report) = classic_pso(
+        &position_max,
+        &position_min,
+        &x,
+        &y,
         100_000,
-        1.0,
+        0.9,
         2.0,
         2.0,
         200,
+        Fano,
+        &PsoOptions {
+            inertia: Inertia::Linear,
+            ..PsoOptions::default()
+        },
+        None,
     )
     .unwrap();
     let duration = start.elapsed();
@@ -29,5 +35,6 @@ fn main() {
     println!("{:?}", best_position);
     println!("{:?}", mean);
     println!("{:?}", stdvar);
+    println!("{} after {} steps", report.stop_reason.name(), report.steps);
     println!("Time elapsed in expensive_function() is: {:?}", duration);
 }
@@ -1,23 +1,448 @@
 mod swarm;
-use self::swarm::Swarm;
+use self::swarm::adaptive::{Adaptation, Adaptive};
+use self::swarm::{BoundHandling, Crossover, Strategy, Swarm};
+use optimizer_core::model::builtin;
+use optimizer_core::python::{restart, termination, with_report, PyCallback};
+use optimizer_core::restart::with_restarts;
+use optimizer_core::seed::seeded;
+use optimizer_core::termination::Monitor;
+use optimizer_core::{
+    check_bounds, Callback, Expression, History, Model, Progress, Report, Restart, StopReason,
+    Termination,
+};
+use pyo3::exceptions::ValueError;
 use pyo3::prelude::*;
 use pyo3::wrap_pyfunction;
+use rand::RngCore;
 
-pub fn evaluate(params: &Vec<f64>, x_data: &Vec<f64>, y_data: &Vec<f64>) -> f64 {
-    (params[0] * x_data[0] - y_data[0]).powf(2.0)
+/// 差分进化的完整流程，返回最佳偏差和最佳位置，以及优化过程的概况。
+/// 设置了重启时，种群满足终止条件或者达到steps代后重新初始化并放入之前最好的结果，粒子数按倍数增大，
+/// 直到用完终止条件中的计算偏差次数或运行时间。
+/// 给出回调函数时每一代之后调用它，它返回true时提前停止。
+/// uncertainty为true时在概况中给出最佳位置处由雅可比矩阵估计的参数不确定度
+#[allow(clippy::too_many_arguments)]
+fn run<M: Model>(
+    model: M,
+    position_max: &[f64],
+    position_min: &[f64],
+    x_data: &[f64],
+    y_data: &[f64],
+    num_of_particles: usize,
+    differential_weight: f64,
+    crossover_probability: f64,
+    steps: usize,
+    strategy: &str,
+    crossover: &str,
+    adaptation: Option<&str>,
+    bound_handling: &str,
+    termination: &Termination,
+    restart: Option<Restart>,
+    seed: Option<u64>,
+    history: bool,
+    uncertainty: bool,
+    mut callback: Option<Callback>,
+) -> Result<((f64, Vec<f64>), Report), String> {
+    check_bounds(&model, position_max, position_min)?;
+    if !(differential_weight > 0.0 && differential_weight.is_finite()) {
+        return Err(format!(
+            "differential weight must be positive, but got {}",
+            differential_weight
+        ));
+    }
+    if !(0.0..=1.0).contains(&crossover_probability) {
+        return Err(format!(
+            "crossover probability must be between 0 and 1, but got {}",
+            crossover_probability
+        ));
+    }
+    let strategy_name = strategy;
+    let strategy = Strategy::from_name(strategy_name)?;
+    let crossover = Crossover::from_name(crossover)?;
+    let adaptation = match adaptation {
+        Some(name) => Some(Adaptation::from_name(name)?),
+        None => None,
+    };
+    let bound_handling = BoundHandling::from_name(bound_handling)?;
+    if adaptation.is_some() && num_of_particles < Adaptive::min_particles() {
+        return Err(format!(
+            "adaptive differential evolution needs at least {} particles",
+            Adaptive::min_particles()
+        ));
+    }
+    if adaptation.is_none() && num_of_particles < strategy.min_particles() {
+        return Err(format!(
+            "mutation strategy `{}` needs at least {} particles",
+            strategy_name,
+            strategy.min_particles()
+        ));
+    }
+    // 每次运行的种子由给出的种子派生，重启后的种群不会重复之前的演化
+    let mut seeds = seeded(seed);
+    let mut run_once = |num_of_particles: usize,
+                        termination: &Termination,
+                        incumbent: Option<&(f64, Vec<f64>)>| {
+        let mut a_swarm = Swarm::new(
+            num_of_particles,
+            position_max,
+            position_min,
+            x_data,
+            y_data,
+            &model,
+            bound_handling,
+            Some(seeds.next_u64()),
+        );
+        if let Some((_, position)) = incumbent {
+            a_swarm.inject(position);
+        }
+        // 自适应时差分权重和交叉概率只作为记忆的初值
+        let mut adaptive = adaptation.map(|adaptation| {
+            Adaptive::new(
+                adaptation,
+                num_of_particles,
+                differential_weight,
+                crossover_probability,
+            )
+        });
+        let mut monitor = Monitor::new(termination, position_max, position_min);
+        let mut report = Report {
+            stop_reason: StopReason::MaxSteps,
+            steps,
+            evaluations: 0,
+            restarts: 0,
+            history: History::default(),
+            uncertainty: None,
+        };
+        for step in 0..steps {
+            match adaptive.as_mut() {
+                Some(adaptive) => adaptive.evolution(&mut a_swarm, crossover, step, steps),
+                None => a_swarm.evolution(
+                    differential_weight,
+                    crossover_probability,
+                    strategy,
+                    crossover,
+                ),
+            }
+            let spread = if monitor.needs_spread() || history || callback.is_some() {
+                a_swarm.spread()
+            } else {
+                Vec::new()
+            };
+            let (best_loss, best_position) = a_swarm.result();
+            if history {
+                report
+                    .history
+                    .record(best_loss, &best_position, a_swarm.mean_loss(), &spread);
+            }
+            if let Some(callback) = callback.as_mut() {
+                let progress = Progress {
+                    step: step + 1,
+                    best_loss,
+                    best_position: &best_position,
+                    mean_loss: a_swarm.mean_loss(),
+                    spread: &spread,
+                    evaluations: a_swarm.evaluations(),
+                };
+                if callback(&progress) {
+                    report.stop_reason = StopReason::Callback;
+                    report.steps = step + 1;
+                    break;
+                }
+            }
+            if let Some(stop_reason) = monitor.check(best_loss, &spread, a_swarm.evaluations()) {
+                report.stop_reason = stop_reason;
+                report.steps = step + 1;
+                break;
+            }
+        }
+        let (loss, position) = a_swarm.result_within_bounds();
+        report.evaluations = a_swarm.evaluations();
+        Ok(((loss, position), loss, report))
+    };
+    let (result, mut report) = match restart {
+        Some(restart) => with_restarts(&restart, termination, num_of_particles, run_once),
+        None => run_once(num_of_particles, termination, None)
+            .map(|(result, _, report)| (result, report)),
+    }?;
+    if uncertainty {
+        report.uncertainty = Some(optimizer_core::uncertainty(
+            &model, &result.1, x_data, y_data,
+        ));
+    }
+    Ok((result, report))
+}
+/// 以内置模型（"biexponential"、"fano" 或 "lorentzian"，默认为"biexponential"）拟合数据的差分进化
+///
+/// 变异策略可选 "rand/1"、"rand/2"、"best/1"、"best/2"、"current-to-best/1" 和 "current-to-pbest/1"，
+/// 交叉方式可选 "binomial" 和 "exponential"。
+/// 自适应可选 "jade"、"shade" 和 "lshade"，此时变异策略固定为 current-to-pbest/1，
+/// 差分权重和交叉概率作为自适应的初值。
+/// 试验向量越界时的处理方式可选 "clip"、"reflect"、"random"、"midpoint" 和 "penalty"。
+/// stall_steps、loss_tolerance、relative_tolerance、position_tolerance、max_evaluations
+/// 和time_limit（秒）是提前终止的条件，full_output为True时在结果后面附上包含停止原因、
+/// 实际代数、计算偏差次数和重启次数的字典。
+/// restarts为True时停滞后重新初始化种群并放入之前最好的结果，粒子数乘以population_growth，
+/// 最多为初始的16倍，并且不超过剩余预算，直到用完max_evaluations或time_limit，返回最好的一次运行的结果。
+/// 给出seed时结果可以逐位重复，与线程数无关。
+/// history为True时在字典中附上每一代的历史最佳偏差、最佳位置、平均偏差和位置的标准差，
+/// 重启时各次运行的记录依次连在一起。
+/// callback是每一代之后调用的Python函数 callback(step, best_loss, best_position, statistics)，
+/// statistics包含平均偏差、位置的标准差和计算偏差的次数，返回真值时提前停止。
+/// uncertainty为True时在字典中附上最佳位置处由雅可比矩阵估计的标准误差、协方差矩阵、
+/// 相关系数矩阵和残差方差
+#[pyfunction(
+    strategy = "\"rand/1\"",
+    crossover = "\"binomial\"",
+    adaptation = "None",
+    bound_handling = "\"clip\"",
+    stall_steps = "None",
+    loss_tolerance = "0.0",
+    relative_tolerance = "0.0",
+    position_tolerance = "None",
+    max_evaluations = "None",
+    time_limit = "None",
+    full_output = "false",
+    restarts = "false",
+    population_growth = "2.0",
+    seed = "None",
+    history = "false",
+    uncertainty = "false",
+    callback = "None",
+    model = "\"biexponential\""
+)]
+#[allow(clippy::too_many_arguments)]
+fn de(
+    py: Python,
+    position_max: Vec<f64>,
+    position_min: Vec<f64>,
+    x_data: Vec<f64>,
+    y_data: Vec<f64>,
+    num_of_particles: usize,
+    differential_weight: f64,
+    crossover_probability: f64,
+    steps: usize,
+    strategy: &str,
+    crossover: &str,
+    adaptation: Option<&str>,
+    bound_handling: &str,
+    stall_steps: Option<usize>,
+    loss_tolerance: f64,
+    relative_tolerance: f64,
+    position_tolerance: Option<f64>,
+    max_evaluations: Option<usize>,
+    time_limit: Option<f64>,
+    full_output: bool,
+    restarts: bool,
+    population_growth: f64,
+    seed: Option<u64>,
+    history: bool,
+    uncertainty: bool,
+    callback: Option<PyObject>,
+    model: &str,
+) -> PyResult<PyObject> {
+    let termination = termination(
+        stall_steps,
+        loss_tolerance,
+        relative_tolerance,
+        position_tolerance,
+        max_evaluations,
+        time_limit,
+    )?;
+    let restart = restart(restarts, population_growth)?;
+    let model =
+        builtin(model).ok_or_else(|| ValueError::py_err(format!("unknown model `{}`", model)))?;
+    let mut callback = callback.map(PyCallback::new);
+    let mut observe = callback
+        .as_mut()
+        .map(|callback| move |progress: &Progress| callback.observe(progress));
+    // 优化期间释放GIL，回调函数被调用时再获取GIL
+    let result = py.allow_threads(|| {
+        run(
+            &*model,
+            &position_max,
+            &position_min,
+            &x_data,
+            &y_data,
+            num_of_particles,
+            differential_weight,
+            crossover_probability,
+            steps,
+            strategy,
+            crossover,
+            adaptation,
+            bound_handling,
+            &termination,
+            restart,
+            seed,
+            history,
+            uncertainty,
+            observe.as_mut().map(|observe| observe as Callback),
+        )
+    });
+    if let Some(callback) = callback.as_mut() {
+        callback.take_error(py)?;
+    }
+    let (result, report) = result.map_err(ValueError::py_err)?;
+    with_report(py, result, &report, full_output || history || uncertainty)
 }
-#[pyfunction]
-fn de(position_max:Vec<f64>,position_min:Vec<f64>,x_data:Vec<f64>,y_data:Vec<f64>,num_of_particles:usize,differential_weight:f64,crossover_probability:f64,steps:usize)->PyResult<(f64,Vec<f64>)>{
-    let mut a_swarm = Swarm::new(num_of_particles, &position_max, &position_min);
-    for _ in 0..steps{
-        a_swarm.evolution(differential_weight, crossover_probability, &x_data, &y_data);
-    }
-    let (best_loss,best_position) = a_swarm.result(&x_data, &y_data);
-    Ok((best_loss,best_position))
+/// 以表达式字符串为模型的差分进化，不给出参数名时按参数在表达式中出现的顺序排列
+#[pyfunction(
+    strategy = "\"rand/1\"",
+    crossover = "\"binomial\"",
+    adaptation = "None",
+    bound_handling = "\"clip\"",
+    stall_steps = "None",
+    loss_tolerance = "0.0",
+    relative_tolerance = "0.0",
+    position_tolerance = "None",
+    max_evaluations = "None",
+    time_limit = "None",
+    full_output = "false",
+    restarts = "false",
+    population_growth = "2.0",
+    seed = "None",
+    history = "false",
+    uncertainty = "false",
+    callback = "None"
+)]
+#[allow(clippy::too_many_arguments)]
+fn expression_de(
+    py: Python,
+    expression: &str,
+    param_names: Option<Vec<String>>,
+    position_max: Vec<f64>,
+    position_min: Vec<f64>,
+    x_data: Vec<f64>,
+    y_data: Vec<f64>,
+    num_of_particles: usize,
+    differential_weight: f64,
+    crossover_probability: f64,
+    steps: usize,
+    strategy: &str,
+    crossover: &str,
+    adaptation: Option<&str>,
+    bound_handling: &str,
+    stall_steps: Option<usize>,
+    loss_tolerance: f64,
+    relative_tolerance: f64,
+    position_tolerance: Option<f64>,
+    max_evaluations: Option<usize>,
+    time_limit: Option<f64>,
+    full_output: bool,
+    restarts: bool,
+    population_growth: f64,
+    seed: Option<u64>,
+    history: bool,
+    uncertainty: bool,
+    callback: Option<PyObject>,
+) -> PyResult<PyObject> {
+    let termination = termination(
+        stall_steps,
+        loss_tolerance,
+        relative_tolerance,
+        position_tolerance,
+        max_evaluations,
+        time_limit,
+    )?;
+    let restart = restart(restarts, population_growth)?;
+    let model = match param_names {
+        Some(param_names) => Expression::new(expression, &param_names),
+        None => Expression::with_inferred_params(expression),
+    }
+    .map_err(ValueError::py_err)?;
+    let mut callback = callback.map(PyCallback::new);
+    let mut observe = callback
+        .as_mut()
+        .map(|callback| move |progress: &Progress| callback.observe(progress));
+    // 优化期间释放GIL，回调函数被调用时再获取GIL
+    let result = py.allow_threads(|| {
+        run(
+            model,
+            &position_max,
+            &position_min,
+            &x_data,
+            &y_data,
+            num_of_particles,
+            differential_weight,
+            crossover_probability,
+            steps,
+            strategy,
+            crossover,
+            adaptation,
+            bound_handling,
+            &termination,
+            restart,
+            seed,
+            history,
+            uncertainty,
+            observe.as_mut().map(|observe| observe as Callback),
+        )
+    });
+    if let Some(callback) = callback.as_mut() {
+        callback.take_error(py)?;
+    }
+    let (result, report) = result.map_err(ValueError::py_err)?;
+    with_report(py, result, &report, full_output || history || uncertainty)
 }
 #[pymodule]
 fn differential_evolution(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_wrapped(wrap_pyfunction!(de))?;
+    m.add_wrapped(wrap_pyfunction!(expression_de))?;
 
     Ok(())
 }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use optimizer_core::model::Lorentzian;
+
+    #[test]
+    fn seed_independent_of_threads() {
+        let x = (0..20).map(|i| i as f64 * 0.5).collect::<Vec<f64>>();
+        let y = x.iter().map(|x| 2.0 / (1.0 + x * x)).collect::<Vec<f64>>();
+        for (strategy, crossover, adaptation, bound_handling) in [
+            ("rand/1", "binomial", None, "random"),
+            (
+                "current-to-pbest/1",
+                "exponential",
+                Some("lshade"),
+                "reflect",
+            ),
+        ]
+        .iter()
+        {
+            let fit = || {
+                run(
+                    Lorentzian,
+                    &[5.0, 5.0],
+                    &[0.1, 0.1],
+                    &x,
+                    &y,
+                    30,
+                    0.8,
+                    0.9,
+                    40,
+                    strategy,
+                    crossover,
+                    *adaptation,
+                    bound_handling,
+                    &Termination::default(),
+                    None,
+                    Some(5),
+                    false,
+                    false,
+                    None,
+                )
+                .unwrap()
+                .0
+            };
+            let with_threads = |threads| {
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()
+                    .unwrap()
+                    .install(fit)
+            };
+            assert_eq!(with_threads(1), with_threads(4), "{}", strategy);
+        }
+    }
+}
@@ -1,114 +1,352 @@
+pub mod adaptive;
+mod bound;
 mod crossover_res;
 mod mutation_force;
 mod particle;
+mod strategy;
+pub use self::bound::BoundHandling;
+pub use self::crossover_res::Crossover;
 use self::crossover_res::CrossoverResult;
-use super::evaluate;
 use self::mutation_force::MutationForce;
 use self::particle::Particle;
-use rand::seq::SliceRandom;
-use rand::thread_rng;
+pub use self::strategy::Strategy;
+use optimizer_core::model::comparable_loss;
+use optimizer_core::seed::{seeded, stream};
+use optimizer_core::Model;
+use rand::rngs::StdRng;
 use rayon::prelude::*;
+use std::cmp::Ordering;
+/// 每个粒子在一代中使用的控制参数
+#[derive(Debug, Clone, Copy)]
+pub struct Control {
+    pub differential_weight: f64,
+    pub crossover_probability: f64,
+    pub strategy: Strategy,
+}
 #[derive(Debug)]
-pub struct Swarm {
+pub struct Swarm<M: Model> {
     pub particles: Vec<Particle>,
     mutation_force_list: Vec<MutationForce>,
     crossover_res_list: Vec<CrossoverResult>,
     number_of_particles: usize,
+    /// 参数的上下界和越界时的处理方式
+    position_max: Vec<f64>,
+    position_min: Vec<f64>,
+    bound_handling: BoundHandling,
+    /// 计算偏差的次数
+    evaluations: usize,
+    /// 种群整体使用的随机数发生器
+    rng: StdRng,
+    /// 每个粒子各自的随机数流
+    particle_rngs: Vec<StdRng>,
+    /// 待拟合的数据
+    x_data: Vec<f64>,
+    y_data: Vec<f64>,
+    model: M,
 }
-impl Swarm {
-    pub fn new(number_of_particles: usize, up_bound: &Vec<f64>, down_bound: &Vec<f64>) -> Self {
+impl<M: Model> Swarm<M> {
+    /// 创建种群，给出种子时结果可以重复
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        number_of_particles: usize,
+        up_bound: &[f64],
+        down_bound: &[f64],
+        x_data: &[f64],
+        y_data: &[f64],
+        model: M,
+        bound_handling: BoundHandling,
+        seed: Option<u64>,
+    ) -> Self {
         let dims = up_bound.len();
+        // 按粒子的下标依次派生随机数流
+        let mut rng = seeded(seed);
+        let mut particle_rngs = (0..number_of_particles)
+            .map(|_| stream(&mut rng))
+            .collect::<Vec<StdRng>>();
         let mut particles: Vec<Particle> = Vec::new();
         let mut mutation_force_list: Vec<MutationForce> = Vec::new();
         let mut crossover_res_list: Vec<CrossoverResult> = Vec::new();
         particles.reserve(number_of_particles);
         mutation_force_list.reserve(number_of_particles);
         crossover_res_list.reserve(number_of_particles);
-        for _ in 0..number_of_particles {
-            particles.push(Particle::new(up_bound, down_bound));
+        for particle_rng in particle_rngs.iter_mut() {
+            particles.push(Particle::new(
+                particle_rng,
+                up_bound,
+                down_bound,
+                x_data,
+                y_data,
+                &model,
+            ));
             mutation_force_list.push(MutationForce::new());
             crossover_res_list.push(CrossoverResult::new(dims));
         }
         Swarm {
-            particles: particles,
-            mutation_force_list: mutation_force_list,
-            crossover_res_list: crossover_res_list,
-            number_of_particles: number_of_particles,
+            particles,
+            mutation_force_list,
+            crossover_res_list,
+            number_of_particles,
+            position_max: up_bound.to_vec(),
+            position_min: down_bound.to_vec(),
+            bound_handling,
+            evaluations: number_of_particles,
+            rng,
+            particle_rngs,
+            x_data: x_data.to_vec(),
+            y_data: y_data.to_vec(),
+            model,
         }
     }
-    fn mutation(&mut self, differential_weight: f64) {
+    /// 按偏差从小到大排好序的粒子下标
+    fn ranking(&self) -> Vec<usize> {
+        let mut ranking: Vec<usize> = (0..self.number_of_particles).collect();
+        ranking.sort_by(|a, b| {
+            self.particles[*a]
+                .loss
+                .partial_cmp(&self.particles[*b].loss)
+                .unwrap_or(Ordering::Equal)
+        });
+        ranking
+    }
+    fn mutation(&mut self, controls: &[Control], archive: &[Vec<f64>]) {
+        let ranking = self.ranking();
+        let particles = &self.particles;
+        // 下标超出粒子数时指向外部存档
+        let position = |j: usize| match particles.get(j) {
+            Some(p) => p.position.as_slice(),
+            None => archive[j - particles.len()].as_slice(),
+        };
+        self.mutation_force_list
+            .par_iter_mut()
+            .zip(self.particle_rngs.par_iter_mut())
+            .zip(controls)
+            .enumerate()
+            .for_each(|(i, ((x, rng), control))| {
+                let (base, differences) = control.strategy.choose(rng, i, &ranking, archive.len());
+                let differences = differences
+                    .iter()
+                    .map(|(a, b)| (position(*a), position(*b)))
+                    .collect::<Vec<(&[f64], &[f64])>>();
+                x.calc_mutation_force(control.differential_weight, position(base), &differences)
+            })
+    }
+    fn crossover(&mut self, controls: &[Control], crossover: Crossover) {
         let particles = &self.particles;
-        self.mutation_force_list.par_iter_mut().for_each(|x| {
-            let mut rng = thread_rng();
-            let particle1 = particles.choose(&mut rng).unwrap();
-            let particle2 = particles.choose(&mut rng).unwrap();
-            let particle3 = particles.choose(&mut rng).unwrap();
-            x.calc_mutation_force(differential_weight, particle1, particle2, particle3)
-        })
-    }
-    fn croseeover(&mut self, crossover_probability: f64) {
-        let particles = &mut self.particles;
         let mutation_force_list = &self.mutation_force_list;
-        let mut rng = thread_rng();
-        let nums: Vec<usize> = (0..self.number_of_particles).collect();
-        let force_change_index: usize = *nums.choose(&mut rng).unwrap();
+        let position_max = &self.position_max;
+        let position_min = &self.position_min;
+        let bound_handling = self.bound_handling;
         self.crossover_res_list
             .par_iter_mut()
-            .zip(
-                particles
-                    .par_iter()
-                    .zip(mutation_force_list)
-                    .collect::<Vec<(&Particle, &MutationForce)>>(),
-            )
-            .for_each(|(c, (p, m))| {
-                c.calc_crossover_res(crossover_probability, p, m, force_change_index)
+            .zip(self.particle_rngs.par_iter_mut())
+            .zip(particles)
+            .zip(mutation_force_list)
+            .zip(controls)
+            .for_each(|((((c, rng), p), m), control)| {
+                c.calc_crossover_res(rng, control.crossover_probability, crossover, p, m);
+                c.repair_bounds(rng, bound_handling, p, position_max, position_min);
             })
     }
-    fn select(&mut self, x_data: &Vec<f64>, y_data: &Vec<f64>) {
+    fn select(&mut self) -> Vec<Option<(Vec<f64>, f64)>> {
         let crossover_res_list = &self.crossover_res_list;
+        let x_data = &self.x_data;
+        let y_data = &self.y_data;
+        let model = &self.model;
+        let position_max = &self.position_max;
+        let position_min = &self.position_min;
+        let bound_handling = self.bound_handling;
+        let loss = |position: &[f64]| {
+            bound_handling.penalized_loss(
+                model.calc_loss(position, x_data, y_data),
+                position,
+                position_max,
+                position_min,
+            )
+        };
         self.particles
             .par_iter_mut()
             .zip(crossover_res_list)
-            .for_each(|(p, c)| {
-                p.select(x_data, y_data, c);
-            });
+            .map(|(p, c)| p.select(c, loss))
+            .collect()
+    }
+    /// 以每个粒子各自的控制参数演化一代，返回每个粒子被替换掉的旧位置和偏差的下降量
+    pub fn generation(
+        &mut self,
+        controls: &[Control],
+        crossover: Crossover,
+        archive: &[Vec<f64>],
+    ) -> Vec<Option<(Vec<f64>, f64)>> {
+        self.mutation(controls, archive);
+        self.crossover(controls, crossover);
+        self.evaluations += self.number_of_particles;
+        self.select()
     }
     pub fn evolution(
         &mut self,
         differential_weight: f64,
         crossover_probability: f64,
-        x_data: &Vec<f64>,
-        y_data: &Vec<f64>,
+        strategy: Strategy,
+        crossover: Crossover,
     ) {
-        self.mutation(differential_weight);
-        self.croseeover(crossover_probability);
-        self.select(x_data, y_data);
+        let control = Control {
+            differential_weight,
+            crossover_probability,
+            strategy,
+        };
+        self.generation(&vec![control; self.number_of_particles], crossover, &[]);
+    }
+    pub fn number_of_particles(&self) -> usize {
+        self.number_of_particles
+    }
+    /// 计算偏差的次数
+    pub fn evaluations(&self) -> usize {
+        self.evaluations
     }
-    pub fn result(&self, x_data: &Vec<f64>, y_data: &Vec<f64>)->(f64,Vec<f64>) {
+    /// 所有粒子当前位置的平均偏差
+    pub fn mean_loss(&self) -> f64 {
+        self.particles.iter().map(|p| p.loss).sum::<f64>() / self.number_of_particles as f64
+    }
+    /// 粒子位置在每个维度上的标准差
+    pub fn spread(&self) -> Vec<f64> {
+        let n = self.number_of_particles as f64;
+        let dims = self.position_max.len();
+        (0..dims)
+            .map(|j| {
+                let mean = self.particles.iter().map(|p| p.position[j]).sum::<f64>() / n;
+                let variance = self
+                    .particles
+                    .iter()
+                    .map(|p| (p.position[j] - mean).powi(2))
+                    .sum::<f64>()
+                    / n;
+                variance.sqrt()
+            })
+            .collect()
+    }
+    /// 去掉偏差最大的粒子，只保留size个
+    pub fn shrink(&mut self, size: usize) {
+        if size >= self.number_of_particles {
+            return;
+        }
+        let mut keep = vec![false; self.number_of_particles];
+        self.ranking()
+            .into_iter()
+            .take(size)
+            .for_each(|i| keep[i] = true);
+        let mut keep_iter = keep.iter();
+        self.particles.retain(|_| *keep_iter.next().unwrap());
+        self.mutation_force_list.truncate(size);
+        self.crossover_res_list.truncate(size);
+        self.particle_rngs.truncate(size);
+        self.number_of_particles = size;
+    }
+    /// 把偏差最大的粒子换成给定的位置，例如重启前最好的结果，使新的种群不会丢掉它
+    pub fn inject(&mut self, position: &[f64]) {
+        let worst = *self.ranking().last().unwrap();
+        let loss = self.bound_handling.penalized_loss(
+            self.model.calc_loss(position, &self.x_data, &self.y_data),
+            position,
+            &self.position_max,
+            &self.position_min,
+        );
+        self.particles[worst] = Particle {
+            position: position.to_vec(),
+            loss: comparable_loss(loss),
+        };
+        self.evaluations += 1;
+    }
+    /// 范围内的最佳偏差和最佳位置。罚函数方式下粒子可能越界，此时取范围内最好的粒子
+    /// 和截断到边界上的最佳位置中更好的一个，截断后的位置重新计算偏差
+    pub fn result_within_bounds(&mut self) -> (f64, Vec<f64>) {
+        let (best_loss, best_position) = self.result();
+        let position_max = &self.position_max;
+        let position_min = &self.position_min;
+        let inside = |position: &[f64]| {
+            position
+                .iter()
+                .zip(position_max)
+                .zip(position_min)
+                .all(|((p, max), min)| p <= max && p >= min)
+        };
+        if self.bound_handling != BoundHandling::Penalty || inside(&best_position) {
+            return (best_loss, best_position);
+        }
+        let clipped = best_position
+            .iter()
+            .zip(position_max)
+            .zip(position_min)
+            .map(|((p, max), min)| p.max(*min).min(*max))
+            .collect::<Vec<f64>>();
+        let mut result = (
+            comparable_loss(self.model.calc_loss(&clipped, &self.x_data, &self.y_data)),
+            clipped,
+        );
+        self.evaluations += 1;
+        for p in self.particles.iter().filter(|p| inside(&p.position)) {
+            if p.loss < result.0 {
+                result = (p.loss, p.position.clone());
+            }
+        }
+        result
+    }
+    pub fn result(&self) -> (f64, Vec<f64>) {
         let mut best_loss: Option<f64> = None;
         let mut best_position_index: Option<usize> = None;
         self.particles
             .iter()
-            .map(|x| evaluate(&x.position, x_data, y_data))
+            .map(|x| x.loss)
             .enumerate()
             .for_each(|(i, x)| match best_loss {
                 None => {
                     best_loss = Some(x);
                     best_position_index = Some(i);
                 }
-                _ => match x <= best_loss.unwrap() {
-                    true => {
+                _ => {
+                    if x <= best_loss.unwrap() {
                         best_loss = Some(x);
                         best_position_index = Some(i);
                     }
-                    _ => (),
-                },
+                }
             });
-        (best_loss.unwrap(),
+        (
+            best_loss.unwrap(),
             self.particles
                 .get(best_position_index.unwrap())
                 .unwrap()
-                .position.clone())
+                .position
+                .clone(),
+        )
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use optimizer_core::Expression;
 
+    #[test]
+    fn penalty_result_within_bounds() {
+        // 最佳参数a=2在上限之外，罚函数方式下粒子停在a≈1.014处，但结果必须在范围内
+        let model = Expression::with_inferred_params("a*x").unwrap();
+        let x = [1.0, 2.0, 3.0];
+        let y = [2.0, 4.0, 6.0];
+        let mut swarm = Swarm::new(
+            20,
+            &[1.0],
+            &[0.0],
+            &x,
+            &y,
+            &model,
+            BoundHandling::Penalty,
+            Some(1),
+        );
+        for _ in 0..100 {
+            swarm.evolution(0.8, 0.9, Strategy::Rand1, Crossover::Binomial);
+        }
+        assert!(swarm.result().1[0] > 1.0);
+        let evaluations = swarm.evaluations();
+        let (loss, position) = swarm.result_within_bounds();
+        assert_eq!(position, vec![1.0]);
+        assert_eq!(loss, 14.0);
+        assert_eq!(swarm.evaluations(), evaluations + 1);
     }
 }
@@ -0,0 +1,353 @@
+use super::{Control, Crossover, Strategy, Swarm};
+use optimizer_core::Model;
+use rand::Rng;
+/// JADE中控制参数均值的学习率
+const JADE_LEARNING_RATE: f64 = 0.1;
+/// JADE中pbest所占的比例
+const JADE_P_BEST: f64 = 0.05;
+/// SHADE中每个粒子的pbest比例从 [2/NP, 0.2] 中随机选取
+const SHADE_P_BEST_MAX: f64 = 0.2;
+/// L-SHADE中pbest所占的比例
+const LSHADE_P_BEST: f64 = 0.11;
+/// L-SHADE的历史记忆长度
+const LSHADE_MEMORY_SIZE: usize = 6;
+/// L-SHADE外部存档的大小与粒子数之比
+const LSHADE_ARCHIVE_RATE: f64 = 2.6;
+/// L-SHADE粒子数线性减少的终点
+const LSHADE_MIN_PARTICLES: usize = 4;
+/// 控制参数采样分布的尺度
+const SCALE: f64 = 0.1;
+/// 差分权重重新采样的最多次数，仍不是正数时取记忆截断到 [SCALE, 1] 后的值
+const MAX_RESAMPLES: usize = 100;
+/// 自适应差分进化的变体
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Adaptation {
+    /// JADE：控制参数的均值按成功的参数缓慢更新，配合外部存档
+    Jade,
+    /// SHADE：用成功的参数的加权平均依次更新历史记忆
+    Shade,
+    /// L-SHADE：在SHADE的基础上线性减少粒子数
+    LShade,
+}
+impl Adaptation {
+    /// 由名字选取自适应的变体，可选 "jade"、"shade" 和 "lshade"
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "jade" => Ok(Adaptation::Jade),
+            "shade" => Ok(Adaptation::Shade),
+            "lshade" => Ok(Adaptation::LShade),
+            _ => Err(format!("unknown adaptation `{}`", name)),
+        }
+    }
+}
+/// 自适应差分进化的状态，包括控制参数的记忆和外部存档
+///
+/// 变异策略固定为带外部存档的 current-to-pbest/1，每个粒子的差分权重和交叉概率
+/// 分别从以记忆为中心的柯西分布和正态分布中采样
+#[derive(Debug)]
+pub struct Adaptive {
+    adaptation: Adaptation,
+    /// 交叉概率的记忆，None是L-SHADE的终止值，此后从这个位置采样的交叉概率固定为0
+    memory_crossover_probability: Vec<Option<f64>>,
+    /// 差分权重的记忆
+    memory_differential_weight: Vec<f64>,
+    /// 下一次更新的记忆位置
+    memory_index: usize,
+    /// 被试验向量替换掉的父代位置
+    archive: Vec<Vec<f64>>,
+    /// 初始的粒子数
+    initial_particles: usize,
+}
+impl Adaptive {
+    /// 以给定的差分权重和交叉概率作为记忆的初值
+    pub fn new(
+        adaptation: Adaptation,
+        number_of_particles: usize,
+        differential_weight: f64,
+        crossover_probability: f64,
+    ) -> Self {
+        let memory_size = match adaptation {
+            Adaptation::Jade => 1,
+            Adaptation::Shade => number_of_particles,
+            Adaptation::LShade => LSHADE_MEMORY_SIZE,
+        };
+        Adaptive {
+            adaptation,
+            memory_crossover_probability: vec![Some(crossover_probability); memory_size],
+            memory_differential_weight: vec![differential_weight; memory_size],
+            memory_index: 0,
+            archive: Vec::new(),
+            initial_particles: number_of_particles,
+        }
+    }
+    /// 自适应时所需的最少粒子数
+    pub fn min_particles() -> usize {
+        Strategy::CurrentToPBest1(JADE_P_BEST).min_particles()
+    }
+    /// 演化一代，step从0开始计数，steps是总代数
+    pub fn evolution<M: Model>(
+        &mut self,
+        swarm: &mut Swarm<M>,
+        crossover: Crossover,
+        step: usize,
+        steps: usize,
+    ) {
+        let number_of_particles = swarm.number_of_particles();
+        let controls = (0..number_of_particles)
+            .map(|_| self.sample_control(&mut swarm.rng, number_of_particles))
+            .collect::<Vec<Control>>();
+        let replaced = swarm.generation(&controls, crossover, &self.archive);
+        // 收集使偏差下降的控制参数，并把被替换掉的父代放入存档
+        let mut successful_controls = Vec::new();
+        let mut improvements = Vec::new();
+        for (control, r) in controls.iter().zip(replaced) {
+            if let Some((old_position, improvement)) = r {
+                if improvement > 0.0 {
+                    self.archive.push(old_position);
+                }
+                // 父代偏差为无穷大时下降量无法作为权重
+                if improvement > 0.0 && improvement.is_finite() {
+                    successful_controls.push(*control);
+                    improvements.push(improvement);
+                }
+            }
+        }
+        self.update_memory(&successful_controls, &improvements);
+        // L-SHADE的粒子数随代数线性减少
+        if self.adaptation == Adaptation::LShade {
+            let min_particles = LSHADE_MIN_PARTICLES.min(self.initial_particles);
+            let next_size = self.initial_particles as f64
+                - (self.initial_particles - min_particles) as f64 * (step + 1) as f64
+                    / steps as f64;
+            swarm.shrink((next_size.round() as usize).max(min_particles));
+        }
+        // 存档超出上限时随机删去多余的位置
+        let archive_size = self.archive_size(swarm.number_of_particles());
+        while self.archive.len() > archive_size {
+            let i = swarm.rng.gen_range(0, self.archive.len());
+            self.archive.swap_remove(i);
+        }
+    }
+    /// 外部存档的大小上限
+    fn archive_size(&self, number_of_particles: usize) -> usize {
+        match self.adaptation {
+            Adaptation::Jade | Adaptation::Shade => number_of_particles,
+            Adaptation::LShade => {
+                (LSHADE_ARCHIVE_RATE * number_of_particles as f64).round() as usize
+            }
+        }
+    }
+    /// 为一个粒子采样这一代的控制参数
+    fn sample_control<R: Rng>(&self, rng: &mut R, number_of_particles: usize) -> Control {
+        let r = rng.gen_range(0, self.memory_differential_weight.len());
+        // 交叉概率服从正态分布，截断到 [0, 1]
+        let crossover_probability = match self.memory_crossover_probability[r] {
+            Some(mu) => normal(rng, mu, SCALE).clamp(0.0, 1.0),
+            None => 0.0,
+        };
+        // 差分权重服从柯西分布，不大于1，不是正数时重新采样
+        let mu = self.memory_differential_weight[r];
+        let differential_weight = (0..MAX_RESAMPLES)
+            .map(|_| cauchy(rng, mu, SCALE))
+            .find(|f| *f > 0.0)
+            .unwrap_or_else(|| mu.max(SCALE))
+            .min(1.0);
+        let p = match self.adaptation {
+            Adaptation::Jade => JADE_P_BEST,
+            Adaptation::Shade => {
+                let p_min = 2.0 / number_of_particles as f64;
+                if p_min < SHADE_P_BEST_MAX {
+                    rng.gen_range(p_min, SHADE_P_BEST_MAX)
+                } else {
+                    p_min
+                }
+            }
+            Adaptation::LShade => LSHADE_P_BEST,
+        };
+        Control {
+            differential_weight,
+            crossover_probability,
+            strategy: Strategy::CurrentToPBest1(p),
+        }
+    }
+    /// 用这一代成功的控制参数更新记忆，平均值不是有限值时保留原来的记忆
+    fn update_memory(&mut self, successful_controls: &[Control], improvements: &[f64]) {
+        if successful_controls.is_empty() {
+            return;
+        }
+        let differential_weights = successful_controls
+            .iter()
+            .map(|c| c.differential_weight)
+            .collect::<Vec<f64>>();
+        let crossover_probabilities = successful_controls
+            .iter()
+            .map(|c| c.crossover_probability)
+            .collect::<Vec<f64>>();
+        let k = self.memory_index;
+        match self.adaptation {
+            Adaptation::Jade => {
+                // 不加权，交叉概率取算术平均，差分权重取Lehmer平均
+                let weights = vec![1.0; successful_controls.len()];
+                let c = JADE_LEARNING_RATE;
+                let mu = self.memory_crossover_probability[0].unwrap();
+                if let Some(mean) = weighted_mean(&crossover_probabilities, &weights) {
+                    self.memory_crossover_probability[0] = Some((1.0 - c) * mu + c * mean);
+                }
+                if let Some(mean) = weighted_lehmer_mean(&differential_weights, &weights) {
+                    self.memory_differential_weight[0] =
+                        (1.0 - c) * self.memory_differential_weight[0] + c * mean;
+                }
+            }
+            Adaptation::Shade => {
+                // 以偏差的下降量为权重
+                if let Some(mean) = weighted_mean(&crossover_probabilities, improvements) {
+                    self.memory_crossover_probability[k] = Some(mean);
+                }
+                if let Some(mean) = weighted_lehmer_mean(&differential_weights, improvements) {
+                    self.memory_differential_weight[k] = mean;
+                }
+                self.memory_index = (k + 1) % self.memory_differential_weight.len();
+            }
+            Adaptation::LShade => {
+                // 成功的交叉概率全为0时记忆进入终止值
+                let max_crossover_probability =
+                    crossover_probabilities.iter().cloned().fold(0.0, f64::max);
+                self.memory_crossover_probability[k] = match self.memory_crossover_probability[k] {
+                    Some(mu) if max_crossover_probability > 0.0 => Some(
+                        weighted_lehmer_mean(&crossover_probabilities, improvements).unwrap_or(mu),
+                    ),
+                    _ => None,
+                };
+                if let Some(mean) = weighted_lehmer_mean(&differential_weights, improvements) {
+                    self.memory_differential_weight[k] = mean;
+                }
+                self.memory_index = (k + 1) % self.memory_differential_weight.len();
+            }
+        }
+    }
+}
+/// 加权算术平均，权重之和溢出或为0时返回None
+fn weighted_mean(values: &[f64], weights: &[f64]) -> Option<f64> {
+    let sum_of_weights: f64 = weights.iter().sum();
+    let mean = values.iter().zip(weights).map(|(v, w)| v * w).sum::<f64>() / sum_of_weights;
+    Some(mean).filter(|m| m.is_finite())
+}
+/// 加权Lehmer平均 Σw·v² / Σw·v，分母溢出或为0时返回None
+fn weighted_lehmer_mean(values: &[f64], weights: &[f64]) -> Option<f64> {
+    let numerator: f64 = values.iter().zip(weights).map(|(v, w)| w * v * v).sum();
+    let denominator: f64 = values.iter().zip(weights).map(|(v, w)| w * v).sum();
+    Some(numerator / denominator).filter(|m| m.is_finite())
+}
+/// 正态分布的随机数（Box-Muller变换）
+fn normal<R: Rng>(rng: &mut R, mu: f64, sigma: f64) -> f64 {
+    let u1 = 1.0 - rng.gen::<f64>();
+    let u2 = rng.gen::<f64>();
+    mu + sigma * (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+/// 柯西分布的随机数
+fn cauchy<R: Rng>(rng: &mut R, mu: f64, gamma: f64) -> f64 {
+    mu + gamma * (std::f64::consts::PI * (rng.gen::<f64>() - 0.5)).tan()
+}
+#[cfg(test)]
+mod tests {
+    use super::super::BoundHandling;
+    use super::*;
+    use optimizer_core::model::Lorentzian;
+    use optimizer_core::seed::seeded;
+
+    fn control(differential_weight: f64, crossover_probability: f64) -> Control {
+        Control {
+            differential_weight,
+            crossover_probability,
+            strategy: Strategy::CurrentToPBest1(JADE_P_BEST),
+        }
+    }
+
+    #[test]
+    fn jade_memory() {
+        let mut adaptive = Adaptive::new(Adaptation::Jade, 10, 0.5, 0.5);
+        adaptive.update_memory(&[control(1.0, 1.0)], &[1.0]);
+        // 均值以学习率0.1向成功的参数移动
+        assert!((adaptive.memory_crossover_probability[0].unwrap() - 0.55).abs() < 1e-12);
+        assert!((adaptive.memory_differential_weight[0] - 0.55).abs() < 1e-12);
+    }
+
+    #[test]
+    fn shade_memory() {
+        let mut adaptive = Adaptive::new(Adaptation::Shade, 3, 0.5, 0.5);
+        adaptive.update_memory(&[control(0.5, 0.2), control(1.0, 0.6)], &[1.0, 3.0]);
+        // 交叉概率取加权算术平均，差分权重取加权Lehmer平均，依次写入下一个位置
+        assert!((adaptive.memory_crossover_probability[0].unwrap() - 0.5).abs() < 1e-12);
+        assert!((adaptive.memory_differential_weight[0] - 3.25 / 3.5).abs() < 1e-12);
+        assert_eq!(adaptive.memory_crossover_probability[1], Some(0.5));
+        assert_eq!(adaptive.memory_index, 1);
+        // 权重之和为0时保留原来的记忆
+        adaptive.update_memory(&[control(0.7, 0.7)], &[0.0]);
+        assert_eq!(adaptive.memory_crossover_probability[1], Some(0.5));
+        assert_eq!(adaptive.memory_differential_weight[1], 0.5);
+        assert_eq!(adaptive.memory_index, 2);
+    }
+
+    #[test]
+    fn lshade_terminal_memory() {
+        let mut adaptive = Adaptive::new(Adaptation::LShade, 20, 0.5, 0.5);
+        adaptive.update_memory(&[control(0.5, 0.0), control(0.6, 0.0)], &[1.0, 1.0]);
+        assert_eq!(adaptive.memory_crossover_probability[0], None);
+        // 终止值之后从这个位置采样的交叉概率固定为0
+        let mut rng = seeded(Some(1));
+        adaptive.memory_crossover_probability = vec![None; LSHADE_MEMORY_SIZE];
+        for _ in 0..20 {
+            assert_eq!(
+                adaptive.sample_control(&mut rng, 20).crossover_probability,
+                0.0
+            );
+        }
+    }
+
+    #[test]
+    fn sampling_terminates() {
+        let mut rng = seeded(Some(1));
+        for mu in &[f64::NAN, -1e6] {
+            let mut adaptive = Adaptive::new(Adaptation::Jade, 10, 0.5, 0.5);
+            adaptive.memory_differential_weight[0] = *mu;
+            let f = adaptive.sample_control(&mut rng, 10).differential_weight;
+            assert_eq!(f, SCALE);
+        }
+        let adaptive = Adaptive::new(Adaptation::Jade, 10, 0.5, 0.5);
+        for _ in 0..1000 {
+            let control = adaptive.sample_control(&mut rng, 10);
+            assert!(control.differential_weight > 0.0 && control.differential_weight <= 1.0);
+            assert!((0.0..=1.0).contains(&control.crossover_probability));
+        }
+    }
+
+    #[test]
+    fn lshade_population_shrinks() {
+        let x = (0..11).map(|x| x as f64).collect::<Vec<f64>>();
+        let y = x
+            .iter()
+            .map(|x| 100000.0 / (10000.0 + x * x))
+            .collect::<Vec<f64>>();
+        let mut swarm = Swarm::new(
+            40,
+            &[200000.0, 20000.0],
+            &[0.0, 0.0],
+            &x,
+            &y,
+            Lorentzian,
+            BoundHandling::Clip,
+            Some(1),
+        );
+        let mut adaptive = Adaptive::new(Adaptation::LShade, 40, 0.5, 0.5);
+        let steps = 9;
+        let mut sizes = Vec::new();
+        for step in 0..steps {
+            adaptive.evolution(&mut swarm, Crossover::Binomial, step, steps);
+            sizes.push(swarm.number_of_particles());
+            assert!(adaptive.archive.len() <= adaptive.archive_size(swarm.number_of_particles()));
+        }
+        // 粒子数从40线性减少到4
+        assert_eq!(sizes, vec![36, 32, 28, 24, 20, 16, 12, 8, 4]);
+        assert_eq!(swarm.particles.len(), 4);
+    }
+}
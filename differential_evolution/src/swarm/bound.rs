@@ -0,0 +1,144 @@
+use optimizer_core::boundary::reflect;
+use rand::Rng;
+/// 罚函数的权重，越界量以边界范围为单位
+const PENALTY_WEIGHT: f64 = 1e3;
+/// 试验向量越过边界时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BoundHandling {
+    /// 截断到边界上
+    Clip,
+    /// 以边界为镜面反射回范围内
+    Reflect,
+    /// 在范围内重新随机取值
+    Random,
+    /// 取父代与越过的边界的中点
+    Midpoint,
+    /// 保留越界的位置，但在偏差上加上与越界量平方成正比的惩罚，最终结果仍在范围内
+    Penalty,
+}
+impl BoundHandling {
+    /// 由名字选取边界处理方式，可选 "clip"、"reflect"、"random"、"midpoint" 和 "penalty"
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "clip" => Ok(BoundHandling::Clip),
+            "reflect" => Ok(BoundHandling::Reflect),
+            "random" => Ok(BoundHandling::Random),
+            "midpoint" => Ok(BoundHandling::Midpoint),
+            "penalty" => Ok(BoundHandling::Penalty),
+            _ => Err(format!("unknown bound handling `{}`", name)),
+        }
+    }
+    /// 把试验向量中越界的分量移回范围内，parent是试验向量对应的父代位置
+    pub fn repair<R: Rng>(
+        self,
+        rng: &mut R,
+        trial: &mut [f64],
+        parent: &[f64],
+        position_max: &[f64],
+        position_min: &[f64],
+    ) {
+        for (j, u) in trial.iter_mut().enumerate() {
+            let (max, min) = (position_max[j], position_min[j]);
+            if *u <= max && *u >= min {
+                continue;
+            }
+            *u = match self {
+                BoundHandling::Clip => {
+                    if *u > max {
+                        max
+                    } else {
+                        min
+                    }
+                }
+                BoundHandling::Reflect => reflect(*u, max, min),
+                BoundHandling::Random => min + rng.gen::<f64>() * (max - min),
+                BoundHandling::Midpoint => {
+                    if *u > max {
+                        (parent[j] + max) / 2.0
+                    } else {
+                        (parent[j] + min) / 2.0
+                    }
+                }
+                BoundHandling::Penalty => *u,
+            };
+        }
+    }
+    /// 计入罚函数后的偏差，只有Penalty方式会改变偏差
+    pub fn penalized_loss(
+        self,
+        loss: f64,
+        position: &[f64],
+        position_max: &[f64],
+        position_min: &[f64],
+    ) -> f64 {
+        if self != BoundHandling::Penalty {
+            return loss;
+        }
+        let violation: f64 = position
+            .iter()
+            .zip(position_max.iter().zip(position_min))
+            .map(|(u, (max, min))| {
+                // 上下限相等时越界量不除以范围
+                let range = if max > min { max - min } else { 1.0 };
+                let v = if *u > *max {
+                    (u - max) / range
+                } else if *u < *min {
+                    (min - u) / range
+                } else {
+                    0.0
+                };
+                v * v
+            })
+            .sum();
+        if violation == 0.0 {
+            return loss;
+        }
+        // 加性和乘性的惩罚同时使用，使惩罚在偏差的任何量级上都起作用
+        let penalty = PENALTY_WEIGHT * violation;
+        loss * (1.0 + penalty) + penalty
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use optimizer_core::seed::seeded;
+
+    #[test]
+    fn repair_stays_inside() {
+        let mut rng = seeded(Some(1));
+        let modes = [
+            BoundHandling::Clip,
+            BoundHandling::Reflect,
+            BoundHandling::Random,
+            BoundHandling::Midpoint,
+        ];
+        for mode in modes.iter() {
+            let mut trial = vec![-25.0, 0.5, 13.0, 2.0];
+            mode.repair(
+                &mut rng,
+                &mut trial,
+                &[0.2, 0.5, 0.9, 1.0],
+                &[1.0, 1.0, 1.0, 1.0],
+                &[0.0, 0.0, 0.0, 1.0],
+            );
+            assert!(trial[..3].iter().all(|u| *u >= 0.0 && *u <= 1.0));
+            assert_eq!(trial[1], 0.5);
+            assert_eq!(trial[3], 1.0);
+        }
+    }
+
+    #[test]
+    fn penalty_with_zero_range() {
+        let penalty = BoundHandling::Penalty;
+        // 上下限相等时惩罚仍是有限值，偏差为0时也不会得到NaN
+        let loss = penalty.penalized_loss(0.0, &[1.5], &[1.0], &[1.0]);
+        assert!(loss.is_finite() && loss > 0.0);
+        assert_eq!(penalty.penalized_loss(2.0, &[1.0], &[1.0], &[1.0]), 2.0);
+        assert_eq!(penalty.penalized_loss(2.0, &[0.5], &[1.0], &[0.0]), 2.0);
+        assert!(penalty.penalized_loss(2.0, &[1.5], &[1.0], &[0.0]) > 2.0);
+        assert_eq!(
+            BoundHandling::Clip.penalized_loss(2.0, &[1.5], &[1.0], &[0.0]),
+            2.0
+        );
+    }
+}
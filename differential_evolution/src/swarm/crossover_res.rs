@@ -1,6 +1,25 @@
+use super::bound::BoundHandling;
 use super::mutation_force::MutationForce;
 use super::particle::Particle;
 use rand::Rng;
+/// 交叉方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Crossover {
+    /// 每个维度独立地以交叉概率取变异向量的分量
+    Binomial,
+    /// 从随机的维度开始，连续地取变异向量的分量，每多取一个的概率为交叉概率
+    Exponential,
+}
+impl Crossover {
+    /// 由名字选取交叉方式，可选 "binomial" 和 "exponential"
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "binomial" => Ok(Crossover::Binomial),
+            "exponential" => Ok(Crossover::Exponential),
+            _ => Err(format!("unknown crossover `{}`", name)),
+        }
+    }
+}
 #[derive(Debug)]
 pub struct CrossoverResult {
     pub crossover_result: Vec<Option<f64>>,
@@ -11,27 +30,130 @@ impl CrossoverResult {
             crossover_result: vec![None; dims],
         }
     }
-    pub fn calc_crossover_res(
+    pub fn calc_crossover_res<R: Rng>(
         &mut self,
+        rng: &mut R,
         cross_probability: f64,
+        crossover: Crossover,
         particle: &Particle,
         mutation_force: &MutationForce,
-        force_change_index: usize,
     ) {
-        let r = rand::thread_rng().gen::<f64>();
+        let dims = self.crossover_result.len();
+        let mutation_force = mutation_force.mutation_force.as_ref().unwrap();
+        // 每个试验向量各自选取一个一定来自变异向量的维度
+        let force_change_index = rng.gen_range(0, dims);
+        let mut from_mutation = vec![false; dims];
+        match crossover {
+            Crossover::Binomial => {
+                for (j, f) in from_mutation.iter_mut().enumerate() {
+                    *f = j == force_change_index || rng.gen::<f64>() < cross_probability;
+                }
+            }
+            Crossover::Exponential => {
+                let mut length = 0;
+                loop {
+                    from_mutation[(force_change_index + length) % dims] = true;
+                    length += 1;
+                    if length >= dims || rng.gen::<f64>() >= cross_probability {
+                        break;
+                    }
+                }
+            }
+        }
         for (j, u) in self.crossover_result.iter_mut().enumerate() {
-            if r <= cross_probability || j == force_change_index {
-                *u = Some(
-                    mutation_force
-                        .mutation_force
-                        .as_ref()
-                        .unwrap()
-                        .get(j)
-                        .unwrap()
-                        .clone(),
-                )
+            if from_mutation[j] {
+                *u = Some(mutation_force[j])
             } else {
-                *u = Some(particle.position.get(j).unwrap().clone())
+                *u = Some(particle.position[j])
+            }
+        }
+    }
+    /// 按边界处理方式修正越界的试验向量
+    pub fn repair_bounds<R: Rng>(
+        &mut self,
+        rng: &mut R,
+        bound_handling: BoundHandling,
+        particle: &Particle,
+        position_max: &[f64],
+        position_min: &[f64],
+    ) {
+        let mut trial = self
+            .crossover_result
+            .iter()
+            .map(|x| x.unwrap())
+            .collect::<Vec<f64>>();
+        bound_handling.repair(
+            rng,
+            &mut trial,
+            &particle.position,
+            position_max,
+            position_min,
+        );
+        for (u, t) in self.crossover_result.iter_mut().zip(trial) {
+            *u = Some(t);
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use optimizer_core::seed::seeded;
+
+    /// 父代的分量都是0，变异向量的分量都是1，返回取自变异向量的维度
+    fn mutant_dims<R: Rng>(
+        rng: &mut R,
+        crossover: Crossover,
+        cross_probability: f64,
+        dims: usize,
+    ) -> Vec<usize> {
+        let particle = Particle {
+            position: vec![0.0; dims],
+            loss: 0.0,
+        };
+        let mutation_force = MutationForce {
+            mutation_force: Some(vec![1.0; dims]),
+        };
+        let mut result = CrossoverResult::new(dims);
+        result.calc_crossover_res(
+            rng,
+            cross_probability,
+            crossover,
+            &particle,
+            &mutation_force,
+        );
+        (0..dims)
+            .filter(|j| result.crossover_result[*j] == Some(1.0))
+            .collect()
+    }
+
+    #[test]
+    fn at_least_one_mutant_dim() {
+        let mut rng = seeded(Some(1));
+        for crossover in &[Crossover::Binomial, Crossover::Exponential] {
+            for dims in 1..6 {
+                for _ in 0..100 {
+                    // 交叉概率为0时正好取一个维度，为1时取全部维度
+                    assert_eq!(mutant_dims(&mut rng, *crossover, 0.0, dims).len(), 1);
+                    assert_eq!(mutant_dims(&mut rng, *crossover, 1.0, dims).len(), dims);
+                    assert!(!mutant_dims(&mut rng, *crossover, 0.3, dims).is_empty());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn exponential_is_contiguous() {
+        let mut rng = seeded(Some(1));
+        let dims = 8;
+        for _ in 0..200 {
+            let taken = mutant_dims(&mut rng, Crossover::Exponential, 0.5, dims);
+            // 没有取全部维度时，取到的维度在环上连续，即恰好有一个维度的前一个维度没有取到
+            let starts = taken
+                .iter()
+                .filter(|j| !taken.contains(&((*j + dims - 1) % dims)))
+                .count();
+            if taken.len() < dims {
+                assert_eq!(starts, 1);
             }
         }
     }
@@ -1,4 +1,3 @@
-use super::particle::Particle;
 #[derive(Debug)]
 pub struct MutationForce {
     pub mutation_force: Option<Vec<f64>>,
@@ -9,22 +8,21 @@ impl MutationForce {
             mutation_force: None,
         }
     }
+    /// 变异向量 v = base + F * Σ(a - b)
     pub fn calc_mutation_force(
         &mut self,
         differential_weight: f64,
-        particle1: &Particle,
-        particle2: &Particle,
-        particle3: &Particle,
+        base: &[f64],
+        differences: &[(&[f64], &[f64])],
     ) {
-        self.mutation_force = Some(
-            particle1
-                .position
-                .iter()
-                .zip(particle2.position.iter())
-                .map(|(a, b)| (a - b) * differential_weight)
-                .zip(particle3.position.iter())
-                .map(|(a, b)| a + b)
-                .collect(),
-        )
+        let mut mutation_force = base.to_vec();
+        for (a, b) in differences {
+            mutation_force
+                .iter_mut()
+                .zip(a.iter())
+                .zip(b.iter())
+                .for_each(|((v, a), b)| *v += (a - b) * differential_weight);
+        }
+        self.mutation_force = Some(mutation_force)
     }
 }
@@ -1,41 +1,57 @@
 use super::crossover_res::CrossoverResult;
-use crate::evaluate;
+use optimizer_core::model::comparable_loss;
+use optimizer_core::Model;
 use rand::Rng;
 #[derive(Debug)]
 pub struct Particle {
     pub position: Vec<f64>,
+    /// 当前位置的偏差
+    pub loss: f64,
 }
 impl Particle {
-    pub fn new(position_max: &Vec<f64>, position_min: &Vec<f64>) -> Self {
+    pub fn new<R: Rng, M: Model>(
+        rng: &mut R,
+        position_max: &[f64],
+        position_min: &[f64],
+        x_data: &[f64],
+        y_data: &[f64],
+        model: &M,
+    ) -> Self {
         let position_range: Vec<f64> = position_max
             .iter()
             .zip(position_min.iter())
             .map(|(a, b)| a - b)
             .collect();
-        let mut rng = rand::thread_rng();
         let position: Vec<f64> = position_range
             .iter()
             .map(|a| rng.gen::<f64>() * a)
             .zip(position_min.iter())
             .map(|(a, b)| a + b)
             .collect();
-        Particle { position: position }
+        let loss = comparable_loss(model.calc_loss(&position, x_data, y_data));
+        Particle { position, loss }
     }
-    pub fn select(
+    /// 试验向量不差于当前位置时接受它，返回被替换掉的旧位置和偏差的下降量
+    ///
+    /// loss 计算位置的偏差，可以包含越界的惩罚
+    pub fn select<F: Fn(&[f64]) -> f64>(
         &mut self,
-        x_data: &Vec<f64>,
-        y_data: &Vec<f64>,
         crossover_res: &CrossoverResult,
-    ) {
-        let origin = evaluate(&self.position, x_data, y_data);
+        loss: F,
+    ) -> Option<(Vec<f64>, f64)> {
         let new_position = crossover_res
             .crossover_result
             .iter()
             .map(|x| x.unwrap())
             .collect::<Vec<f64>>();
-        let new_fit = evaluate(&new_position, x_data, y_data);
-        if new_fit <= origin {
-            self.position = new_position;
+        let new_fit = comparable_loss(loss(&new_position));
+        if new_fit <= self.loss {
+            let improvement = self.loss - new_fit;
+            let old_position = std::mem::replace(&mut self.position, new_position);
+            self.loss = new_fit;
+            Some((old_position, improvement))
+        } else {
+            None
         }
     }
 }
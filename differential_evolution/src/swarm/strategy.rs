@@ -0,0 +1,172 @@
+use rand::seq::index::sample;
+use rand::Rng;
+/// current-to-pbest/1 默认从偏差最小的前10%的粒子中选取pbest
+pub const DEFAULT_P_BEST: f64 = 0.1;
+/// 变异策略
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Strategy {
+    /// v = r1 + F * (r2 - r3)
+    Rand1,
+    /// v = r1 + F * (r2 - r3) + F * (r4 - r5)
+    Rand2,
+    /// v = best + F * (r1 - r2)
+    Best1,
+    /// v = best + F * (r1 - r2) + F * (r3 - r4)
+    Best2,
+    /// v = x + F * (best - x) + F * (r1 - r2)
+    CurrentToBest1,
+    /// v = x + F * (pbest - x) + F * (r1 - r2)，pbest从偏差最小的前p比例的粒子中随机选取，
+    /// r2从粒子群和外部存档的并集中选取
+    CurrentToPBest1(f64),
+}
+impl Strategy {
+    /// 由名字选取变异策略，例如 "rand/1"、"current-to-pbest/1"
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "rand/1" => Ok(Strategy::Rand1),
+            "rand/2" => Ok(Strategy::Rand2),
+            "best/1" => Ok(Strategy::Best1),
+            "best/2" => Ok(Strategy::Best2),
+            "current-to-best/1" => Ok(Strategy::CurrentToBest1),
+            "current-to-pbest/1" => Ok(Strategy::CurrentToPBest1(DEFAULT_P_BEST)),
+            _ => Err(format!("unknown mutation strategy `{}`", name)),
+        }
+    }
+    /// 随机选取的粒子个数
+    fn num_of_random(self) -> usize {
+        match self {
+            Strategy::Rand1 => 3,
+            Strategy::Rand2 => 5,
+            Strategy::Best1 => 2,
+            Strategy::Best2 => 4,
+            Strategy::CurrentToBest1 => 2,
+            Strategy::CurrentToPBest1(_) => 2,
+        }
+    }
+    /// 随机选取的粒子和目标粒子互不相同，所需的最少粒子数
+    pub fn min_particles(self) -> usize {
+        self.num_of_random() + 1
+    }
+    /// 为目标粒子选出变异的基向量和差分向量的下标
+    ///
+    /// ranking 是按偏差从小到大排好序的粒子下标，随机选取的粒子互不相同且不是目标粒子。
+    /// 不小于粒子数的下标指向外部存档中的位置，存档的大小为archive_len
+    pub fn choose<R: Rng>(
+        self,
+        rng: &mut R,
+        target: usize,
+        ranking: &[usize],
+        archive_len: usize,
+    ) -> (usize, Vec<(usize, usize)>) {
+        let number_of_particles = ranking.len();
+        let mut r = distinct_indices(rng, number_of_particles, target, self.num_of_random());
+        let best = ranking[0];
+        match self {
+            Strategy::Rand1 => (r[0], vec![(r[1], r[2])]),
+            Strategy::Rand2 => (r[0], vec![(r[1], r[2]), (r[3], r[4])]),
+            Strategy::Best1 => (best, vec![(r[0], r[1])]),
+            Strategy::Best2 => (best, vec![(r[0], r[1]), (r[2], r[3])]),
+            Strategy::CurrentToBest1 => (target, vec![(best, target), (r[0], r[1])]),
+            Strategy::CurrentToPBest1(p) => {
+                let top = ((p * number_of_particles as f64).round() as usize).max(1);
+                let pbest = ranking[rng.gen_range(0, top.min(number_of_particles))];
+                // r2 可以来自外部存档，但仍然不能与目标粒子和r1相同
+                if archive_len > 0 {
+                    r[1] = loop {
+                        let j = rng.gen_range(0, number_of_particles + archive_len);
+                        if j != target && j != r[0] {
+                            break j;
+                        }
+                    };
+                }
+                (target, vec![(pbest, target), (r[0], r[1])])
+            }
+        }
+    }
+}
+/// 从 0..number_of_particles 中选出k个互不相同且不等于target的下标
+fn distinct_indices<R: Rng>(
+    rng: &mut R,
+    number_of_particles: usize,
+    target: usize,
+    k: usize,
+) -> Vec<usize> {
+    sample(rng, number_of_particles - 1, k)
+        .into_iter()
+        .map(|j| if j >= target { j + 1 } else { j })
+        .collect()
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use optimizer_core::seed::seeded;
+
+    const STRATEGIES: [Strategy; 6] = [
+        Strategy::Rand1,
+        Strategy::Rand2,
+        Strategy::Best1,
+        Strategy::Best2,
+        Strategy::CurrentToBest1,
+        Strategy::CurrentToPBest1(DEFAULT_P_BEST),
+    ];
+
+    #[test]
+    fn distinct_donors() {
+        let mut rng = seeded(Some(1));
+        for strategy in STRATEGIES.iter() {
+            // 粒子数正好是所需的最少粒子数时也要选出互不相同的下标
+            let n = strategy.min_particles();
+            let ranking = (0..n).rev().collect::<Vec<usize>>();
+            for _ in 0..200 {
+                let target = rng.gen_range(0, n);
+                let (base, differences) = strategy.choose(&mut rng, target, &ranking, 0);
+                let flat = differences
+                    .iter()
+                    .flat_map(|(a, b)| vec![*a, *b])
+                    .collect::<Vec<usize>>();
+                // 除了best和target以外，基向量和差分向量中的下标都是随机选取的
+                let random = match strategy {
+                    Strategy::Rand1 | Strategy::Rand2 => [vec![base], flat].concat(),
+                    Strategy::Best1 | Strategy::Best2 => {
+                        assert_eq!(base, ranking[0]);
+                        flat
+                    }
+                    Strategy::CurrentToBest1 | Strategy::CurrentToPBest1(_) => {
+                        assert_eq!(base, target);
+                        assert_eq!(flat[1], target);
+                        flat[2..].to_vec()
+                    }
+                };
+                assert_eq!(random.len(), strategy.num_of_random());
+                assert!(random.iter().all(|j| *j != target && *j < n));
+                for (k, j) in random.iter().enumerate() {
+                    assert!(!random[..k].contains(j));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pbest_and_archive() {
+        let mut rng = seeded(Some(1));
+        let n = 20;
+        let ranking = (0..n).collect::<Vec<usize>>();
+        let strategy = Strategy::CurrentToPBest1(0.1);
+        let mut from_archive = false;
+        for _ in 0..500 {
+            let target = rng.gen_range(0, n);
+            let (base, differences) = strategy.choose(&mut rng, target, &ranking, 5);
+            assert_eq!(base, target);
+            let (pbest, current) = differences[0];
+            // pbest来自偏差最小的前10%，即前两个粒子
+            assert!(pbest < 2);
+            assert_eq!(current, target);
+            // r2可以来自存档，但不与目标粒子和r1相同
+            let (r1, r2) = differences[1];
+            assert!(r1 < n && r1 != target);
+            assert!(r2 < n + 5 && r2 != target && r2 != r1);
+            from_archive |= r2 >= n;
+        }
+        assert!(from_archive);
+    }
+}
@@ -0,0 +1,166 @@
+use crate::model::Model;
+use rand::Rng;
+/// 自助法重新生成数据集的方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resampling {
+    /// 在最佳拟合的预测值上加上有放回抽取的残差
+    Residual,
+    /// 有放回地抽取数据点
+    Pairs,
+}
+#[allow(clippy::derivable_impls)]
+impl Default for Resampling {
+    fn default() -> Self {
+        Resampling::Residual
+    }
+}
+impl Resampling {
+    /// 由名字选取重抽样方式，可选 "residual" 和 "pairs"
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "residual" => Ok(Resampling::Residual),
+            "pairs" => Ok(Resampling::Pairs),
+            _ => Err(format!("unknown resampling `{}`", name)),
+        }
+    }
+    /// 由最佳位置和原始数据生成一个重抽样的数据集
+    pub fn resample<R: Rng, M: Model>(
+        self,
+        rng: &mut R,
+        model: &M,
+        best_position: &[f64],
+        x_data: &[f64],
+        y_data: &[f64],
+    ) -> (Vec<f64>, Vec<f64>) {
+        let n = x_data.len();
+        match self {
+            Resampling::Residual => {
+                let y_pred = x_data
+                    .iter()
+                    .map(|x| model.eval(*x, best_position))
+                    .collect::<Vec<f64>>();
+                let residuals = y_data
+                    .iter()
+                    .zip(&y_pred)
+                    .map(|(y, y_pred)| y - y_pred)
+                    .collect::<Vec<f64>>();
+                let y_resampled = y_pred
+                    .iter()
+                    .map(|y_pred| y_pred + residuals[rng.gen_range(0, n)])
+                    .collect();
+                (x_data.to_vec(), y_resampled)
+            }
+            Resampling::Pairs => (0..n)
+                .map(|_| {
+                    let i = rng.gen_range(0, n);
+                    (x_data[i], y_data[i])
+                })
+                .unzip(),
+        }
+    }
+}
+/// 自助法的设置
+#[derive(Debug, Clone, Copy)]
+pub struct Bootstrap {
+    /// 重抽样方式
+    pub resampling: Resampling,
+    /// 重新拟合的次数
+    pub replicates: usize,
+    /// 置信区间的置信水平
+    pub confidence: f64,
+    /// 重新拟合时初始粒子在最佳位置附近的范围，以各维度上下限之间的距离为单位
+    pub warm_radius: f64,
+}
+impl Bootstrap {
+    /// 检查设置，重新拟合的次数必须是正数，置信水平在0和1之间，初始范围是正数
+    pub fn new(
+        resampling: Resampling,
+        replicates: usize,
+        confidence: f64,
+        warm_radius: f64,
+    ) -> Result<Self, String> {
+        if replicates == 0 {
+            return Err("bootstrap needs at least one replicate".to_string());
+        }
+        if !(confidence > 0.0 && confidence < 1.0) {
+            return Err(format!(
+                "confidence must be between 0 and 1, but got {}",
+                confidence
+            ));
+        }
+        if !(warm_radius > 0.0 && warm_radius.is_finite()) {
+            return Err(format!(
+                "warm start radius must be positive, but got {}",
+                warm_radius
+            ));
+        }
+        Ok(Bootstrap {
+            resampling,
+            replicates,
+            confidence,
+            warm_radius,
+        })
+    }
+}
+/// 自助法的结果
+#[derive(Debug, Clone)]
+pub struct BootstrapResult {
+    /// 每次重新拟合得到的最佳位置
+    pub samples: Vec<Vec<f64>>,
+    /// 每个参数的百分位数置信区间的下限
+    pub lower: Vec<f64>,
+    /// 每个参数的百分位数置信区间的上限
+    pub upper: Vec<f64>,
+}
+impl BootstrapResult {
+    /// 由重新拟合的最佳位置算出每个参数的百分位数置信区间
+    pub fn new(samples: Vec<Vec<f64>>, confidence: f64) -> Self {
+        let num_of_params = samples.first().map_or(0, |s| s.len());
+        let (lower, upper) = (0..num_of_params)
+            .map(|j| {
+                let mut values = samples.iter().map(|s| s[j]).collect::<Vec<f64>>();
+                values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+                (
+                    percentile(&values, (1.0 - confidence) / 2.0),
+                    percentile(&values, (1.0 + confidence) / 2.0),
+                )
+            })
+            .unzip();
+        BootstrapResult {
+            samples,
+            lower,
+            upper,
+        }
+    }
+}
+/// 排好序的数据的分位数，在相邻的两个数据之间线性插值
+pub fn percentile(sorted: &[f64], q: f64) -> f64 {
+    let position = q * (sorted.len() - 1) as f64;
+    let below = position.floor() as usize;
+    let above = position.ceil() as usize;
+    sorted[below] + (sorted[above] - sorted[below]) * (position - below as f64)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentile_endpoints() {
+        let sorted = [1.0, 2.0, 4.0, 8.0];
+        assert_eq!(percentile(&sorted, 0.0), 1.0);
+        assert_eq!(percentile(&sorted, 1.0), 8.0);
+        assert_eq!(percentile(&sorted, 0.5), 3.0);
+        assert_eq!(percentile(&[5.0], 0.3), 5.0);
+    }
+
+    #[test]
+    fn percentile_intervals() {
+        let samples = (0..=100).map(|i| vec![i as f64, -i as f64]).collect();
+        let result = BootstrapResult::new(samples, 0.9);
+        let expected = [(5.0, 95.0), (-95.0, -5.0)];
+        for (j, (lower, upper)) in expected.iter().enumerate() {
+            assert!((result.lower[j] - lower).abs() < 1e-9);
+            assert!((result.upper[j] - upper).abs() < 1e-9);
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use rand::Rng;
+/// 粒子越过位置边界时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Boundary {
+    /// 停在边界上，速度的这个分量归零
+    Absorb,
+    /// 以边界为镜面反射回范围内，速度的这个分量反向
+    Reflect,
+    /// 从另一侧的边界进入，速度不变
+    Periodic,
+    /// 在范围内重新随机取值，速度不变
+    Random,
+    /// 越界部分乘以一个随机的衰减系数后反射，速度的这个分量也反向并乘以同一个系数
+    Damping,
+}
+#[allow(clippy::derivable_impls)]
+impl Default for Boundary {
+    fn default() -> Self {
+        Boundary::Absorb
+    }
+}
+impl Boundary {
+    /// 由名字选取边界处理方式，可选 "absorb"、"reflect"、"periodic"、"random" 和 "damping"
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "absorb" => Ok(Boundary::Absorb),
+            "reflect" => Ok(Boundary::Reflect),
+            "periodic" => Ok(Boundary::Periodic),
+            "random" => Ok(Boundary::Random),
+            "damping" => Ok(Boundary::Damping),
+            _ => Err(format!("unknown boundary `{}`", name)),
+        }
+    }
+    /// 把越界的一维位置移回范围内，同时修正速度的这个分量
+    pub fn apply<R: Rng>(
+        self,
+        rng: &mut R,
+        position: &mut f64,
+        velocity: &mut f64,
+        position_max: f64,
+        position_min: f64,
+    ) {
+        let p = *position;
+        if p <= position_max && p >= position_min {
+            return;
+        }
+        match self {
+            Boundary::Absorb => {
+                *position = p.clamp(position_min, position_max);
+                *velocity = 0.0;
+            }
+            Boundary::Reflect => {
+                *position = reflect(p, position_max, position_min);
+                *velocity = -*velocity;
+            }
+            Boundary::Periodic => *position = wrap(p, position_max, position_min),
+            Boundary::Random => {
+                *position = position_min + rng.gen::<f64>() * (position_max - position_min)
+            }
+            Boundary::Damping => {
+                let damping = rng.gen::<f64>();
+                let bound = if p > position_max {
+                    position_max
+                } else {
+                    position_min
+                };
+                // 衰减后仍越界时按反射处理
+                *position = reflect(bound - damping * (p - bound), position_max, position_min);
+                *velocity *= -damping;
+            }
+        }
+    }
+}
+/// 在上下边界之间来回反射，直到落入范围内
+pub fn reflect(p: f64, position_max: f64, position_min: f64) -> f64 {
+    let range = position_max - position_min;
+    if range <= 0.0 {
+        return position_min;
+    }
+    // 反射的周期是两倍的范围
+    let t = (p - position_min).rem_euclid(2.0 * range);
+    if t <= range {
+        position_min + t
+    } else {
+        position_min + 2.0 * range - t
+    }
+}
+/// 把位置按周期边界折回范围内
+pub fn wrap(p: f64, position_max: f64, position_min: f64) -> f64 {
+    let range = position_max - position_min;
+    if range <= 0.0 {
+        return position_min;
+    }
+    position_min + (p - position_min).rem_euclid(range)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-12
+    }
+
+    #[test]
+    fn reflect_far_outside() {
+        // 范围 [1, 3]，反射的周期是4
+        assert!(close(reflect(3.5, 3.0, 1.0), 2.5));
+        assert!(close(reflect(0.5, 3.0, 1.0), 1.5));
+        assert!(close(reflect(6.5, 3.0, 1.0), 2.5));
+        assert!(close(reflect(12.5, 3.0, 1.0), 1.5));
+        assert!(close(reflect(-4.5, 3.0, 1.0), 2.5));
+        assert!(close(reflect(2.0, 3.0, 3.0), 3.0));
+    }
+
+    #[test]
+    fn wrap_far_outside() {
+        assert!(close(wrap(3.5, 3.0, 1.0), 1.5));
+        assert!(close(wrap(0.5, 3.0, 1.0), 2.5));
+        assert!(close(wrap(8.5, 3.0, 1.0), 2.5));
+        assert!(close(wrap(-6.5, 3.0, 1.0), 1.5));
+        assert!(close(wrap(2.0, 3.0, 3.0), 3.0));
+    }
+
+    #[test]
+    fn absorb_stops_at_bound() {
+        let mut rng = rand::thread_rng();
+        let (mut position, mut velocity) = (-7.0, -2.0);
+        Boundary::Absorb.apply(&mut rng, &mut position, &mut velocity, 3.0, 1.0);
+        assert_eq!((position, velocity), (1.0, 0.0));
+    }
+
+    #[test]
+    fn reflect_reverses_velocity() {
+        let mut rng = rand::thread_rng();
+        let (mut position, mut velocity) = (9.0, 2.0);
+        Boundary::Reflect.apply(&mut rng, &mut position, &mut velocity, 3.0, 1.0);
+        assert!(close(position, 1.0) && velocity < 0.0);
+    }
+}
@@ -0,0 +1,537 @@
+use crate::model::Model;
+/// 由数学表达式字符串构建的模型，例如 "a1*exp(-x/e1)+a2*exp(-x/e2)"
+///
+/// 自变量固定为x，支持 + - * / ^（或 **）、括号、pi和e两个常数，
+/// 以及 exp ln log log10 sqrt abs sin cos tan asin acos atan sinh cosh tanh
+/// 和两个参数的 pow atan2 min max hypot 等函数。
+/// 表达式在构建时被编译成逆波兰式的指令序列，求值时不再解析字符串。
+#[derive(Debug, Clone)]
+pub struct Expression {
+    /// 参数的名字，顺序与粒子位置的各个维度一致
+    param_names: Vec<String>,
+    /// 编译好的指令序列
+    ops: Vec<Op>,
+    /// 求值时栈的最大深度
+    max_depth: usize,
+}
+impl Expression {
+    /// 由表达式和参数的名字构建模型，表达式中出现未声明的名字或者参数重名时报错
+    pub fn new(expression: &str, param_names: &[String]) -> Result<Self, String> {
+        Self::build(expression, param_names.to_vec(), false)
+    }
+    /// 由表达式构建模型，参数的名字按照在表达式中第一次出现的顺序排列
+    pub fn with_inferred_params(expression: &str) -> Result<Self, String> {
+        Self::build(expression, Vec::new(), true)
+    }
+    fn build(expression: &str, param_names: Vec<String>, infer: bool) -> Result<Self, String> {
+        // 参数的名字不能和自变量、常数或者函数重名，也不能重复
+        for (i, name) in param_names.iter().enumerate() {
+            if is_reserved(name) {
+                return Err(format!("`{}` is reserved and cannot be a parameter", name));
+            }
+            if param_names[..i].contains(name) {
+                return Err(format!("parameter `{}` is declared more than once", name));
+            }
+        }
+        let tokens = tokenize(expression)?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            param_names,
+            infer,
+        };
+        let node = parser.parse_expr()?;
+        if parser.pos != parser.tokens.len() {
+            return Err(format!(
+                "unexpected {:?} in expression",
+                parser.tokens[parser.pos]
+            ));
+        }
+        // 常数折叠之后编译成逆波兰式
+        let mut ops = Vec::new();
+        fold(node).emit(&mut ops);
+        let max_depth = max_depth(&ops);
+        Ok(Expression {
+            param_names: parser.param_names,
+            ops,
+            max_depth,
+        })
+    }
+    /// 用给定的栈求出表达式的值，栈在多次求值之间复用
+    fn run(&self, x: f64, params: &[f64], stack: &mut Vec<f64>) -> f64 {
+        stack.clear();
+        for op in &self.ops {
+            match *op {
+                Op::Const(c) => stack.push(c),
+                Op::X => stack.push(x),
+                Op::Param(i) => stack.push(params[i]),
+                Op::Neg => {
+                    let a = stack.last_mut().unwrap();
+                    *a = -*a;
+                }
+                Op::Powi(n) => {
+                    let a = stack.last_mut().unwrap();
+                    *a = a.powi(n);
+                }
+                Op::Func1(f) => {
+                    let a = stack.last_mut().unwrap();
+                    *a = f(*a);
+                }
+                Op::Binary(op) => {
+                    let b = stack.pop().unwrap();
+                    let a = stack.last_mut().unwrap();
+                    *a = op.apply(*a, b);
+                }
+                Op::Func2(f) => {
+                    let b = stack.pop().unwrap();
+                    let a = stack.last_mut().unwrap();
+                    *a = f(*a, b);
+                }
+            }
+        }
+        stack[0]
+    }
+}
+impl Model for Expression {
+    fn num_of_params(&self) -> usize {
+        self.param_names.len()
+    }
+    fn param_names(&self) -> Vec<String> {
+        self.param_names.clone()
+    }
+    fn eval(&self, x: f64, params: &[f64]) -> f64 {
+        let mut stack = Vec::with_capacity(self.max_depth);
+        self.run(x, params, &mut stack)
+    }
+    fn calc_loss(&self, params: &[f64], x_data: &[f64], y_data: &[f64]) -> f64 {
+        // 所有数据点共用一个栈
+        let mut stack = Vec::with_capacity(self.max_depth);
+        x_data
+            .iter()
+            .map(|x| self.run(*x, params, &mut stack))
+            .zip(y_data)
+            .map(|(y_pred, y_data)| (y_pred - y_data).powi(2))
+            .sum()
+    }
+}
+/// 二元运算
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+}
+impl BinOp {
+    fn apply(self, a: f64, b: f64) -> f64 {
+        match self {
+            BinOp::Add => a + b,
+            BinOp::Sub => a - b,
+            BinOp::Mul => a * b,
+            BinOp::Div => a / b,
+            BinOp::Pow => a.powf(b),
+        }
+    }
+}
+/// 逆波兰式中的一条指令
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Const(f64),
+    X,
+    Param(usize),
+    Neg,
+    Powi(i32),
+    Binary(BinOp),
+    Func1(fn(f64) -> f64),
+    Func2(fn(f64, f64) -> f64),
+}
+/// 语法树的节点
+#[derive(Debug)]
+enum Node {
+    Const(f64),
+    X,
+    Param(usize),
+    Neg(Box<Node>),
+    Binary(BinOp, Box<Node>, Box<Node>),
+    Func1(fn(f64) -> f64, Box<Node>),
+    Func2(fn(f64, f64) -> f64, Box<Node>, Box<Node>),
+}
+impl Node {
+    /// 将语法树按后序遍历写成逆波兰式
+    fn emit(self, ops: &mut Vec<Op>) {
+        match self {
+            Node::Const(c) => ops.push(Op::Const(c)),
+            Node::X => ops.push(Op::X),
+            Node::Param(i) => ops.push(Op::Param(i)),
+            Node::Neg(a) => {
+                a.emit(ops);
+                ops.push(Op::Neg);
+            }
+            Node::Binary(BinOp::Pow, a, b) => match *b {
+                // 整数次幂用powi计算
+                Node::Const(n) if n.fract() == 0.0 && n.abs() <= i32::MAX as f64 => {
+                    a.emit(ops);
+                    ops.push(Op::Powi(n as i32));
+                }
+                b => {
+                    a.emit(ops);
+                    b.emit(ops);
+                    ops.push(Op::Binary(BinOp::Pow));
+                }
+            },
+            Node::Binary(op, a, b) => {
+                a.emit(ops);
+                b.emit(ops);
+                ops.push(Op::Binary(op));
+            }
+            Node::Func1(f, a) => {
+                a.emit(ops);
+                ops.push(Op::Func1(f));
+            }
+            Node::Func2(f, a, b) => {
+                a.emit(ops);
+                b.emit(ops);
+                ops.push(Op::Func2(f));
+            }
+        }
+    }
+}
+/// 常数折叠，把不含x和参数的子树直接算出来
+fn fold(node: Node) -> Node {
+    match node {
+        Node::Neg(a) => match fold(*a) {
+            Node::Const(c) => Node::Const(-c),
+            a => Node::Neg(Box::new(a)),
+        },
+        Node::Binary(op, a, b) => match (fold(*a), fold(*b)) {
+            (Node::Const(a), Node::Const(b)) => Node::Const(op.apply(a, b)),
+            (a, b) => Node::Binary(op, Box::new(a), Box::new(b)),
+        },
+        Node::Func1(f, a) => match fold(*a) {
+            Node::Const(c) => Node::Const(f(c)),
+            a => Node::Func1(f, Box::new(a)),
+        },
+        Node::Func2(f, a, b) => match (fold(*a), fold(*b)) {
+            (Node::Const(a), Node::Const(b)) => Node::Const(f(a, b)),
+            (a, b) => Node::Func2(f, Box::new(a), Box::new(b)),
+        },
+        leaf => leaf,
+    }
+}
+/// 逆波兰式求值时栈的最大深度
+fn max_depth(ops: &[Op]) -> usize {
+    let mut depth = 0usize;
+    let mut max = 0;
+    for op in ops {
+        match op {
+            Op::Const(_) | Op::X | Op::Param(_) => depth += 1,
+            Op::Binary(_) | Op::Func2(_) => depth -= 1,
+            Op::Neg | Op::Powi(_) | Op::Func1(_) => (),
+        }
+        max = max.max(depth);
+    }
+    max
+}
+/// 单参数函数
+fn func1(name: &str) -> Option<fn(f64) -> f64> {
+    let f: fn(f64) -> f64 = match name {
+        "exp" => f64::exp,
+        "ln" | "log" => f64::ln,
+        "log10" => f64::log10,
+        "sqrt" => f64::sqrt,
+        "abs" => f64::abs,
+        "sin" => f64::sin,
+        "cos" => f64::cos,
+        "tan" => f64::tan,
+        "asin" => f64::asin,
+        "acos" => f64::acos,
+        "atan" => f64::atan,
+        "sinh" => f64::sinh,
+        "cosh" => f64::cosh,
+        "tanh" => f64::tanh,
+        _ => return None,
+    };
+    Some(f)
+}
+/// 双参数函数
+fn func2(name: &str) -> Option<fn(f64, f64) -> f64> {
+    let f: fn(f64, f64) -> f64 = match name {
+        "pow" => f64::powf,
+        "atan2" => f64::atan2,
+        "min" => f64::min,
+        "max" => f64::max,
+        "hypot" => f64::hypot,
+        _ => return None,
+    };
+    Some(f)
+}
+/// 常数
+fn constant(name: &str) -> Option<f64> {
+    match name {
+        "pi" => Some(std::f64::consts::PI),
+        "e" => Some(std::f64::consts::E),
+        _ => None,
+    }
+}
+/// 不能用作参数名的名字
+fn is_reserved(name: &str) -> bool {
+    name == "x" || constant(name).is_some() || func1(name).is_some() || func2(name).is_some()
+}
+/// 词法单元
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(f64),
+    Ident(String),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Caret,
+    LParen,
+    RParen,
+    Comma,
+}
+/// 把表达式拆成词法单元
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = expression.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c.is_ascii_digit() || c == '.' {
+            // 数字，允许科学计数法 1.5e-3
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                let mut j = i + 1;
+                if j < chars.len() && (chars[j] == '+' || chars[j] == '-') {
+                    j += 1;
+                }
+                if j < chars.len() && chars[j].is_ascii_digit() {
+                    while j < chars.len() && chars[j].is_ascii_digit() {
+                        j += 1;
+                    }
+                    i = j;
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            let num = text
+                .parse::<f64>()
+                .map_err(|_| format!("invalid number `{}`", text))?;
+            tokens.push(Token::Num(num));
+            continue;
+        }
+        if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            continue;
+        }
+        let token = match c {
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                i += 1;
+                Token::Caret
+            }
+            '*' => Token::Star,
+            '/' => Token::Slash,
+            '^' => Token::Caret,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            ',' => Token::Comma,
+            _ => return Err(format!("unexpected character `{}` in expression", c)),
+        };
+        tokens.push(token);
+        i += 1;
+    }
+    Ok(tokens)
+}
+/// 递归下降的语法分析器
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// 已知的参数名
+    param_names: Vec<String>,
+    /// 遇到未知的名字时是否把它当作新参数
+    infer: bool,
+}
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(ref token) if *token == expected => Ok(()),
+            Some(token) => Err(format!("expected {:?}, found {:?}", expected, token)),
+            None => Err(format!("expected {:?}, found end of expression", expected)),
+        }
+    }
+    /// expr = term (('+' | '-') term)*
+    fn parse_expr(&mut self) -> Result<Node, String> {
+        let mut node = self.parse_term()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Plus) => BinOp::Add,
+                Some(Token::Minus) => BinOp::Sub,
+                _ => return Ok(node),
+            };
+            self.pos += 1;
+            node = Node::Binary(op, Box::new(node), Box::new(self.parse_term()?));
+        }
+    }
+    /// term = unary (('*' | '/') unary)*
+    fn parse_term(&mut self) -> Result<Node, String> {
+        let mut node = self.parse_unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Star) => BinOp::Mul,
+                Some(Token::Slash) => BinOp::Div,
+                _ => return Ok(node),
+            };
+            self.pos += 1;
+            node = Node::Binary(op, Box::new(node), Box::new(self.parse_unary()?));
+        }
+    }
+    /// unary = ('-' | '+') unary | power
+    fn parse_unary(&mut self) -> Result<Node, String> {
+        match self.peek() {
+            Some(Token::Minus) => {
+                self.pos += 1;
+                Ok(Node::Neg(Box::new(self.parse_unary()?)))
+            }
+            Some(Token::Plus) => {
+                self.pos += 1;
+                self.parse_unary()
+            }
+            _ => self.parse_power(),
+        }
+    }
+    /// power = atom ('^' unary)?，乘方是右结合的，并且优先于负号：-x^2 = -(x^2)
+    fn parse_power(&mut self) -> Result<Node, String> {
+        let base = self.parse_atom()?;
+        if let Some(Token::Caret) = self.peek() {
+            self.pos += 1;
+            let exponent = self.parse_unary()?;
+            return Ok(Node::Binary(BinOp::Pow, Box::new(base), Box::new(exponent)));
+        }
+        Ok(base)
+    }
+    /// atom = number | name | name '(' expr (',' expr)* ')' | '(' expr ')'
+    fn parse_atom(&mut self) -> Result<Node, String> {
+        match self.next() {
+            Some(Token::Num(c)) => Ok(Node::Const(c)),
+            Some(Token::LParen) => {
+                let node = self.parse_expr()?;
+                self.expect(Token::RParen)?;
+                Ok(node)
+            }
+            Some(Token::Ident(name)) => {
+                if let Some(Token::LParen) = self.peek() {
+                    self.pos += 1;
+                    return self.parse_call(&name);
+                }
+                self.parse_name(name)
+            }
+            Some(token) => Err(format!("unexpected {:?} in expression", token)),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+    /// 函数调用，左括号已经读过
+    fn parse_call(&mut self, name: &str) -> Result<Node, String> {
+        let mut args = vec![self.parse_expr()?];
+        while let Some(Token::Comma) = self.peek() {
+            self.pos += 1;
+            args.push(self.parse_expr()?);
+        }
+        self.expect(Token::RParen)?;
+        let mut args = args.into_iter();
+        match (func1(name), func2(name), args.len()) {
+            (Some(f), _, 1) => Ok(Node::Func1(f, Box::new(args.next().unwrap()))),
+            (_, Some(f), 2) => {
+                let a = args.next().unwrap();
+                let b = args.next().unwrap();
+                Ok(Node::Func2(f, Box::new(a), Box::new(b)))
+            }
+            (None, None, _) => Err(format!("unknown function `{}`", name)),
+            (_, _, n) => Err(format!("function `{}` does not take {} arguments", name, n)),
+        }
+    }
+    /// 自变量、参数或者常数
+    fn parse_name(&mut self, name: String) -> Result<Node, String> {
+        if name == "x" {
+            return Ok(Node::X);
+        }
+        if let Some(i) = self.param_names.iter().position(|p| *p == name) {
+            return Ok(Node::Param(i));
+        }
+        if let Some(c) = constant(&name) {
+            return Ok(Node::Const(c));
+        }
+        if self.infer && !is_reserved(&name) {
+            self.param_names.push(name);
+            return Ok(Node::Param(self.param_names.len() - 1));
+        }
+        Err(format!("unknown name `{}` in expression", name))
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(expression: &str, x: f64) -> f64 {
+        Expression::with_inferred_params(expression)
+            .unwrap()
+            .eval(x, &[])
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(eval("1+2*3", 0.0), 7.0);
+        assert_eq!(eval("2*3^2", 0.0), 18.0);
+        assert_eq!(eval("(1+2)*3", 0.0), 9.0);
+        assert_eq!(eval("-x^2", 3.0), -9.0);
+        assert_eq!(eval("-2^2", 0.0), -4.0);
+    }
+
+    #[test]
+    fn associativity() {
+        assert_eq!(eval("2^3^2", 0.0), 512.0);
+        assert_eq!(eval("2**3**2", 0.0), 512.0);
+        assert_eq!(eval("x/2/2", 8.0), 2.0);
+        assert_eq!(eval("x-2-2", 8.0), 4.0);
+    }
+
+    #[test]
+    fn params() {
+        let model = Expression::with_inferred_params("b*x+a").unwrap();
+        assert_eq!(model.param_names(), vec!["b", "a"]);
+        assert_eq!(model.eval(2.0, &[3.0, 1.0]), 7.0);
+    }
+
+    #[test]
+    fn errors() {
+        let names = vec!["a".to_string()];
+        assert!(Expression::new("a*y", &names).is_err());
+        assert!(Expression::new("a*x", &["a".to_string(), "a".to_string()]).is_err());
+        assert!(Expression::new("x", &["sin".to_string()]).is_err());
+        assert!(Expression::with_inferred_params("pow(x)").is_err());
+        assert!(Expression::with_inferred_params("sin(x, 1)").is_err());
+        assert!(Expression::with_inferred_params("foo(x)").is_err());
+        assert!(Expression::with_inferred_params("(x").is_err());
+    }
+}
@@ -0,0 +1,42 @@
+/// 每一代演化之后的记录，用于画收敛曲线
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    /// 历史最佳偏差
+    pub best_loss: Vec<f64>,
+    /// 历史最佳位置
+    pub best_position: Vec<Vec<f64>>,
+    /// 所有粒子当前位置的平均偏差
+    pub mean_loss: Vec<f64>,
+    /// 粒子位置在每个维度上的标准差
+    pub spread: Vec<Vec<f64>>,
+}
+impl History {
+    /// 记下一代的结果
+    pub fn record(
+        &mut self,
+        best_loss: f64,
+        best_position: &[f64],
+        mean_loss: f64,
+        spread: &[f64],
+    ) {
+        self.best_loss.push(best_loss);
+        self.best_position.push(best_position.to_vec());
+        self.mean_loss.push(mean_loss);
+        self.spread.push(spread.to_vec());
+    }
+    /// 接上另一段记录，重启时把各次运行的记录连在一起
+    pub fn append(&mut self, other: &mut History) {
+        self.best_loss.append(&mut other.best_loss);
+        self.best_position.append(&mut other.best_position);
+        self.mean_loss.append(&mut other.mean_loss);
+        self.spread.append(&mut other.spread);
+    }
+    /// 记录的代数
+    pub fn len(&self) -> usize {
+        self.best_loss.len()
+    }
+    /// 是否没有记录
+    pub fn is_empty(&self) -> bool {
+        self.best_loss.is_empty()
+    }
+}
@@ -0,0 +1,125 @@
+use rand::Rng;
+/// 非线性递减的惯性权重的指数
+const NONLINEAR_INDEX: f64 = 1.2;
+/// 随机惯性权重的下限，权重在 [0.5, 1) 中均匀分布
+const RANDOM_INERTIA_MIN: f64 = 0.5;
+/// 惯性权重随代数变化的方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Inertia {
+    /// 固定的惯性权重
+    Constant,
+    /// 从初值线性递减到终值
+    Linear,
+    /// 从初值按 (1 - t/T)^1.2 非线性递减到终值
+    Nonlinear,
+    /// 线性递减，其中的终值乘以Logistic映射产生的混沌序列
+    Chaotic,
+    /// 每一代在 [0.5, 1) 中随机选取
+    Random,
+    /// Clerc-Kennedy收缩因子，χ = 2 / |2 - φ - sqrt(φ² - 4φ)|，φ = c1 + c2 > 4，
+    /// 此时惯性权重为χ，加速系数都乘以χ
+    Constriction,
+}
+#[allow(clippy::derivable_impls)]
+impl Default for Inertia {
+    fn default() -> Self {
+        Inertia::Constant
+    }
+}
+impl Inertia {
+    /// 由名字选取惯性权重的变化方式，可选 "constant"、"linear"、"nonlinear"、"chaotic"、
+    /// "random" 和 "constriction"
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "constant" => Ok(Inertia::Constant),
+            "linear" => Ok(Inertia::Linear),
+            "nonlinear" => Ok(Inertia::Nonlinear),
+            "chaotic" => Ok(Inertia::Chaotic),
+            "random" => Ok(Inertia::Random),
+            "constriction" => Ok(Inertia::Constriction),
+            _ => Err(format!("unknown inertia `{}`", name)),
+        }
+    }
+    /// 以初始的惯性权重、最终的惯性权重和加速系数生成每一代的系数
+    pub fn schedule(self, w: f64, w_end: f64, c1: f64, c2: f64) -> Result<Schedule, String> {
+        if self == Inertia::Constriction && c1 + c2 <= 4.0 {
+            return Err(format!(
+                "constriction factor needs c1 + c2 > 4, but got {}",
+                c1 + c2
+            ));
+        }
+        Ok(Schedule {
+            inertia: self,
+            w,
+            w_end,
+            c1,
+            c2,
+            chaos: None,
+        })
+    }
+}
+/// 每一代的惯性权重和加速系数
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    inertia: Inertia,
+    w: f64,
+    w_end: f64,
+    c1: f64,
+    c2: f64,
+    /// Logistic映射的当前值，第一次使用时随机选取
+    chaos: Option<f64>,
+}
+impl Schedule {
+    /// 第step代（从0开始）的惯性权重和两个加速系数，steps是总代数
+    pub fn coefficients<R: Rng>(
+        &mut self,
+        rng: &mut R,
+        step: usize,
+        steps: usize,
+    ) -> (f64, f64, f64) {
+        // 剩余的进度，从1递减到0
+        let remaining = if steps > 1 {
+            1.0 - step as f64 / (steps - 1) as f64
+        } else {
+            1.0
+        };
+        let linear = self.w_end + (self.w - self.w_end) * remaining;
+        match self.inertia {
+            Inertia::Constant => (self.w, self.c1, self.c2),
+            Inertia::Linear => (linear, self.c1, self.c2),
+            Inertia::Nonlinear => (
+                self.w_end + (self.w - self.w_end) * remaining.powf(NONLINEAR_INDEX),
+                self.c1,
+                self.c2,
+            ),
+            Inertia::Chaotic => {
+                // 避开Logistic映射的不动点和周期点
+                let z = match self.chaos {
+                    Some(z) => 4.0 * z * (1.0 - z),
+                    None => loop {
+                        let z = rng.gen::<f64>();
+                        if ![0.0, 0.25, 0.5, 0.75].contains(&z) {
+                            break z;
+                        }
+                    },
+                };
+                self.chaos = Some(z);
+                (
+                    (self.w - self.w_end) * remaining + self.w_end * z,
+                    self.c1,
+                    self.c2,
+                )
+            }
+            Inertia::Random => (
+                RANDOM_INERTIA_MIN + (1.0 - RANDOM_INERTIA_MIN) * rng.gen::<f64>(),
+                self.c1,
+                self.c2,
+            ),
+            Inertia::Constriction => {
+                let phi = self.c1 + self.c2;
+                let chi = 2.0 / (2.0 - phi - (phi * phi - 4.0 * phi).sqrt()).abs();
+                (chi, chi * self.c1, chi * self.c2)
+            }
+        }
+    }
+}
@@ -0,0 +1,831 @@
+// 最低支持的Rust版本是pyo3 0.9所需的1.54之前的nightly：可以用f64::clamp（1.50），
+// 但不能用#[default]（1.62），所以各个枚举手写impl Default，并在那里局部允许clippy::derivable_impls
+pub mod bootstrap;
+pub mod boundary;
+pub mod expression;
+pub mod history;
+pub mod inertia;
+pub mod mcmc;
+pub mod model;
+pub mod profile;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod restart;
+pub mod seed;
+mod swarm;
+pub mod termination;
+pub mod topology;
+pub mod uncertainty;
+pub mod variant;
+pub use self::bootstrap::{Bootstrap, BootstrapResult, Resampling};
+pub use self::boundary::Boundary;
+pub use self::expression::Expression;
+pub use self::history::History;
+pub use self::inertia::Inertia;
+pub use self::mcmc::{Chains, Sampler};
+pub use self::model::Model;
+use self::profile::Fixed;
+pub use self::profile::{Profile, ProfileResult};
+use self::restart::with_restarts;
+pub use self::restart::Restart;
+use self::seed::seeded;
+pub use self::swarm::particle::Particle;
+pub use self::swarm::{compare, Swarm};
+use self::termination::Monitor;
+pub use self::termination::{Callback, Progress, Report, StopReason, Termination};
+pub use self::topology::Topology;
+pub use self::uncertainty::{uncertainty, Uncertainty};
+pub use self::variant::Variant;
+use self::variant::{APSO_SIGMA_END, APSO_SIGMA_START, QPSO_BETA_END, QPSO_BETA_START};
+use rand::RngCore;
+use rayon::prelude::*;
+/// 历史最佳偏差、历史最佳位置、粒子位置的平均值和标准差
+pub type PsoResult = (f64, Vec<f64>, Vec<f64>, Vec<f64>);
+/// 多个极小值的偏差和位置
+pub type Minima = Vec<(f64, Vec<f64>)>;
+/// 粒子群优化的可选设置
+#[derive(Debug, Clone)]
+pub struct PsoOptions {
+    /// 粒子越过位置边界时的处理方式
+    pub boundary: Boundary,
+    /// 惯性权重随代数变化的方式
+    pub inertia: Inertia,
+    /// 惯性权重递减的终值，初值是传入的w
+    pub w_end: f64,
+    /// 粒子之间的拓扑结构
+    pub topology: Topology,
+    /// 粒子群优化的变体
+    pub variant: Variant,
+    /// 提前终止的条件
+    pub termination: Termination,
+    /// 停滞时重新初始化粒子群的设置，为None时不重启
+    pub restart: Option<Restart>,
+    /// 随机数的种子，给出时结果可以重复，与线程数无关
+    pub seed: Option<u64>,
+    /// 是否在概况中记录每一代的历史最佳偏差、最佳位置、平均偏差和位置的标准差
+    pub history: bool,
+    /// 是否在概况中给出最佳位置处由雅可比矩阵估计的参数不确定度
+    pub uncertainty: bool,
+}
+impl Default for PsoOptions {
+    fn default() -> Self {
+        PsoOptions {
+            boundary: Boundary::default(),
+            inertia: Inertia::default(),
+            w_end: 0.4,
+            topology: Topology::default(),
+            variant: Variant::default(),
+            termination: Termination::default(),
+            restart: None,
+            seed: None,
+            history: false,
+            uncertainty: false,
+        }
+    }
+}
+/// 检查模型是否有参数，位置上下限的维数是否与模型的参数个数一致，上下限是否都是有限值并且下限不大于上限
+pub fn check_bounds<M: Model>(
+    model: &M,
+    position_max: &[f64],
+    position_min: &[f64],
+) -> Result<(), String> {
+    let num_of_params = model.num_of_params();
+    if num_of_params == 0 {
+        return Err("model has no parameters to fit".to_string());
+    }
+    if position_max.len() != num_of_params || position_min.len() != num_of_params {
+        return Err(format!(
+            "model has {} parameters {:?}, but got {} upper bounds and {} lower bounds",
+            num_of_params,
+            model.param_names(),
+            position_max.len(),
+            position_min.len()
+        ));
+    }
+    for ((name, max), min) in model
+        .param_names()
+        .iter()
+        .zip(position_max)
+        .zip(position_min)
+    {
+        if !(max.is_finite() && min.is_finite()) || min > max {
+            return Err(format!(
+                "bounds of parameter `{}` must be finite with min <= max, but got [{}, {}]",
+                name, min, max
+            ));
+        }
+    }
+    Ok(())
+}
+/// 粒子群优化的完整流程，返回历史最佳偏差、历史最佳位置、粒子位置的平均值和标准差，以及优化过程的概况。
+/// 给出回调函数时每一代之后调用它，它返回true时提前停止
+///
+/// 设置了重启时，粒子群满足终止条件或者达到steps代后重新初始化并放入之前最好的结果，粒子数按倍数增大，
+/// 直到用完终止条件中的计算偏差次数或运行时间，返回历史最佳偏差最小的一次运行的结果。
+/// 设置了不确定度时，在概况中给出最终的历史最佳位置处由雅可比矩阵估计的参数不确定度
+#[allow(clippy::too_many_arguments)]
+pub fn classic_pso<M: Model>(
+    position_max: &[f64],
+    position_min: &[f64],
+    x_data: &[f64],
+    y_data: &[f64],
+    num_of_particles: usize,
+    w: f64,
+    c1: f64,
+    c2: f64,
+    steps: usize,
+    model: M,
+    options: &PsoOptions,
+    mut callback: Option<Callback>,
+) -> Result<(PsoResult, Report), String> {
+    // 每次运行的种子由给出的种子派生，重启后的粒子群不会重复之前的运动
+    let mut seeds = seeded(options.seed);
+    let mut run_once =
+        |num_of_particles: usize, termination: &Termination, incumbent: Option<&PsoResult>| {
+            let options = PsoOptions {
+                termination: termination.clone(),
+                seed: Some(seeds.next_u64()),
+                ..options.clone()
+            };
+            let (a_swarm, report) = run(
+                position_max,
+                position_min,
+                x_data,
+                y_data,
+                num_of_particles,
+                w,
+                c1,
+                c2,
+                steps,
+                &model,
+                &options,
+                None,
+                incumbent.map(|result| result.1.as_slice()),
+                callback
+                    .as_mut()
+                    .map(|callback| &mut **callback as Callback),
+            )?;
+            // 测量粒子群的位置的平均值和标准差
+            let (mean_position, standard_var_of_position) = a_swarm.result_evaluate();
+            let loss = a_swarm.global_best_loss;
+            Ok((
+                (
+                    loss,
+                    a_swarm.global_best_position,
+                    mean_position,
+                    standard_var_of_position,
+                ),
+                loss,
+                report,
+            ))
+        };
+    let (result, mut report) = match &options.restart {
+        Some(restart) => with_restarts(restart, &options.termination, num_of_particles, run_once),
+        None => run_once(num_of_particles, &options.termination, None)
+            .map(|(result, _, report)| (result, report)),
+    }?;
+    if options.uncertainty {
+        report.uncertainty = Some(uncertainty(&model, &result.1, x_data, y_data));
+    }
+    Ok((result, report))
+}
+/// 以物种拓扑寻找多个极小值的粒子群优化，返回每个物种的最佳偏差和位置，按偏差从小到大排列，
+/// 以及优化过程的概况
+///
+/// 物种的半径以各维度上下限之间的距离为单位，设置中的拓扑结构、重启和不确定度不起作用
+#[allow(clippy::too_many_arguments)]
+pub fn niching_pso<M: Model>(
+    position_max: &[f64],
+    position_min: &[f64],
+    x_data: &[f64],
+    y_data: &[f64],
+    num_of_particles: usize,
+    w: f64,
+    c1: f64,
+    c2: f64,
+    steps: usize,
+    model: M,
+    niche_radius: f64,
+    options: &PsoOptions,
+    callback: Option<Callback>,
+) -> Result<(Minima, Report), String> {
+    if niche_radius <= 0.0 || niche_radius.is_nan() {
+        return Err(format!(
+            "niche radius must be positive, but got {}",
+            niche_radius
+        ));
+    }
+    let options = PsoOptions {
+        topology: Topology::Species(niche_radius),
+        ..options.clone()
+    };
+    let (a_swarm, report) = run(
+        position_max,
+        position_min,
+        x_data,
+        y_data,
+        num_of_particles,
+        w,
+        c1,
+        c2,
+        steps,
+        model,
+        &options,
+        None,
+        None,
+        callback,
+    )?;
+    Ok((a_swarm.species(niche_radius), report))
+}
+/// 先拟合一次，再以自助法重抽样的数据集重新拟合多次，返回拟合的结果、每个参数的百分位数置信区间，
+/// 以及第一次拟合的概况
+///
+/// 重新拟合时粒子群从第一次拟合的最佳位置附近开始，各次重新拟合在rayon的线程中并行，
+/// 不使用设置中的重启、历史和不确定度
+#[allow(clippy::too_many_arguments)]
+pub fn bootstrap_pso<M: Model>(
+    position_max: &[f64],
+    position_min: &[f64],
+    x_data: &[f64],
+    y_data: &[f64],
+    num_of_particles: usize,
+    w: f64,
+    c1: f64,
+    c2: f64,
+    steps: usize,
+    model: M,
+    options: &PsoOptions,
+    bootstrap: &Bootstrap,
+) -> Result<(PsoResult, BootstrapResult, Report), String> {
+    if x_data.is_empty() {
+        return Err("bootstrap needs at least one data point".to_string());
+    }
+    let (result, report) = classic_pso(
+        position_max,
+        position_min,
+        x_data,
+        y_data,
+        num_of_particles,
+        w,
+        c1,
+        c2,
+        steps,
+        &model,
+        options,
+        None,
+    )?;
+    let best_position = &result.1;
+    // 每次重新拟合的种子由给出的种子依次派生，结果与线程数无关
+    let mut seeds = seeded(options.seed);
+    let replicate_seeds = (0..bootstrap.replicates)
+        .map(|_| seeds.next_u64())
+        .collect::<Vec<u64>>();
+    let samples = replicate_seeds
+        .par_iter()
+        .map(|seed| {
+            let mut rng = seeded(Some(*seed));
+            let (x_resampled, y_resampled) =
+                bootstrap
+                    .resampling
+                    .resample(&mut rng, &model, best_position, x_data, y_data);
+            let options = PsoOptions {
+                seed: Some(rng.next_u64()),
+                history: false,
+                ..options.clone()
+            };
+            let (a_swarm, _) = run(
+                position_max,
+                position_min,
+                &x_resampled,
+                &y_resampled,
+                num_of_particles,
+                w,
+                c1,
+                c2,
+                steps,
+                &model,
+                &options,
+                Some((best_position, bootstrap.warm_radius)),
+                None,
+                None,
+            )?;
+            Ok(a_swarm.global_best_position)
+        })
+        .collect::<Result<Vec<Vec<f64>>, String>>()?;
+    Ok((
+        result,
+        BootstrapResult::new(samples, bootstrap.confidence),
+        report,
+    ))
+}
+/// 先拟合一次，再以最终的粒子群作为行走者，用仿射不变系综采样后验分布，返回拟合的结果、采样的链、
+/// 每个行走者的接受率和每个参数的自相关时间，以及拟合的概况
+///
+/// 似然是高斯的，噪声方差默认取最佳拟合的残差方差，先验在位置上下限之内均匀。
+/// 越过边界的粒子先移回边界上，再加上不超过范围千分之一的高斯扰动，以免行走者挤在一点上，
+/// 粒子数至少是参数个数的两倍。不使用设置中的重启
+#[allow(clippy::too_many_arguments)]
+pub fn mcmc_pso<M: Model>(
+    position_max: &[f64],
+    position_min: &[f64],
+    x_data: &[f64],
+    y_data: &[f64],
+    num_of_particles: usize,
+    w: f64,
+    c1: f64,
+    c2: f64,
+    steps: usize,
+    model: M,
+    options: &PsoOptions,
+    sampler: &Sampler,
+) -> Result<(PsoResult, Chains, Report), String> {
+    if num_of_particles < 2 * position_max.len() {
+        return Err(format!(
+            "ensemble sampling needs at least {} particles as walkers for {} parameters, but got {}",
+            2 * position_max.len(),
+            position_max.len(),
+            num_of_particles
+        ));
+    }
+    let mut seeds = seeded(options.seed);
+    let run_options = PsoOptions {
+        seed: Some(seeds.next_u64()),
+        ..options.clone()
+    };
+    let (a_swarm, mut report) = run(
+        position_max,
+        position_min,
+        x_data,
+        y_data,
+        num_of_particles,
+        w,
+        c1,
+        c2,
+        steps,
+        &model,
+        &run_options,
+        None,
+        None,
+        None,
+    )?;
+    let best_position = &a_swarm.global_best_position;
+    let fit = uncertainty(&model, best_position, x_data, y_data);
+    let noise_variance = match sampler.noise_variance {
+        Some(variance) => variance,
+        None if fit.residual_variance.is_finite() && fit.residual_variance > 0.0 => {
+            fit.residual_variance
+        }
+        None => {
+            return Err(format!(
+                "cannot estimate noise variance from {} data points and {} parameters, give it explicitly",
+                x_data.len(),
+                best_position.len()
+            ))
+        }
+    };
+    let positions = a_swarm
+        .particle_list
+        .iter()
+        .map(|p| {
+            p.position
+                .iter()
+                .zip(position_max)
+                .zip(position_min)
+                .map(|((x, max), min)| x.min(*max).max(*min))
+                .collect()
+        })
+        .collect::<Vec<Vec<f64>>>();
+    let walkers = mcmc::initialize_walkers(
+        &mut seeds,
+        &positions,
+        position_max,
+        position_min,
+        &fit.standard_errors,
+    )?;
+    let chains = mcmc::sample(
+        &model,
+        walkers,
+        position_max,
+        position_min,
+        x_data,
+        y_data,
+        noise_variance,
+        sampler,
+        Some(seeds.next_u64()),
+    );
+    if options.uncertainty {
+        report.uncertainty = Some(fit);
+    }
+    let (mean_position, standard_var_of_position) = a_swarm.result_evaluate();
+    Ok((
+        (
+            a_swarm.global_best_loss,
+            a_swarm.global_best_position,
+            mean_position,
+            standard_var_of_position,
+        ),
+        chains,
+        report,
+    ))
+}
+/// 先拟合一次，再把每个参数依次固定在上下限之间的各个格点上，重新拟合其余参数，
+/// 返回拟合的结果、每个参数的剖面偏差曲线和似然比置信区间，以及第一次拟合的概况
+///
+/// 噪声方差默认取最佳拟合的残差方差。重新拟合时粒子群从第一次拟合的最佳位置附近开始，
+/// 各个格点在rayon的线程中并行，不使用设置中的重启、历史和不确定度
+#[allow(clippy::too_many_arguments)]
+pub fn profile_pso<M: Model>(
+    position_max: &[f64],
+    position_min: &[f64],
+    x_data: &[f64],
+    y_data: &[f64],
+    num_of_particles: usize,
+    w: f64,
+    c1: f64,
+    c2: f64,
+    steps: usize,
+    model: M,
+    options: &PsoOptions,
+    profile: &Profile,
+) -> Result<(PsoResult, ProfileResult, Report), String> {
+    let (result, report) = classic_pso(
+        position_max,
+        position_min,
+        x_data,
+        y_data,
+        num_of_particles,
+        w,
+        c1,
+        c2,
+        steps,
+        &model,
+        options,
+        None,
+    )?;
+    let (best_loss, best_position) = (result.0, &result.1);
+    let num_of_params = best_position.len();
+    let noise_variance = match profile.noise_variance {
+        Some(variance) => variance,
+        None if x_data.len() > num_of_params && best_loss > 0.0 => {
+            best_loss / (x_data.len() - num_of_params) as f64
+        }
+        None => {
+            return Err(format!(
+                "cannot estimate noise variance from {} data points and {} parameters, give it explicitly",
+                x_data.len(),
+                num_of_params
+            ))
+        }
+    };
+    let grids = (0..num_of_params)
+        .map(|i| profile.grid(position_max, position_min, i))
+        .collect::<Vec<Vec<f64>>>();
+    // 每个格点重新拟合的种子由给出的种子依次派生，结果与线程数无关
+    let mut seeds = seeded(options.seed);
+    let tasks = grids
+        .iter()
+        .enumerate()
+        .flat_map(|(i, grid)| grid.iter().map(move |value| (i, *value)))
+        .map(|(i, value)| (i, value, seeds.next_u64()))
+        .collect::<Vec<(usize, f64, u64)>>();
+    let without = |v: &[f64], i: usize| {
+        let mut v = v.to_vec();
+        v.remove(i);
+        v
+    };
+    let losses = tasks
+        .par_iter()
+        .map(|(i, value, seed)| {
+            let fixed = Fixed {
+                model: &model,
+                index: *i,
+                value: *value,
+            };
+            // 只有一个参数时没有需要重新拟合的参数
+            if num_of_params == 1 {
+                return Ok(fixed.calc_loss(&[], x_data, y_data));
+            }
+            let options = PsoOptions {
+                seed: Some(*seed),
+                history: false,
+                ..options.clone()
+            };
+            let (a_swarm, _) = run(
+                &without(position_max, *i),
+                &without(position_min, *i),
+                x_data,
+                y_data,
+                num_of_particles,
+                w,
+                c1,
+                c2,
+                steps,
+                fixed,
+                &options,
+                Some((&without(best_position, *i), profile.warm_radius)),
+                None,
+                None,
+            )?;
+            Ok(a_swarm.global_best_loss)
+        })
+        .collect::<Result<Vec<f64>, String>>()?
+        .chunks(profile.points)
+        .map(|chunk| chunk.to_vec())
+        .collect();
+    let profiles = ProfileResult::new(
+        grids,
+        losses,
+        best_position,
+        best_loss,
+        noise_variance,
+        profile.confidence,
+    );
+    Ok((result, profiles, report))
+}
+/// 创建粒子群并演化，直到达到steps代或者满足终止条件。
+/// 给出warm_start时把粒子放到已知的最佳位置附近，给出incumbent时把最差的粒子换成这个位置
+#[allow(clippy::too_many_arguments)]
+fn run<M: Model>(
+    position_max: &[f64],
+    position_min: &[f64],
+    x_data: &[f64],
+    y_data: &[f64],
+    num_of_particles: usize,
+    w: f64,
+    c1: f64,
+    c2: f64,
+    steps: usize,
+    model: M,
+    options: &PsoOptions,
+    warm_start: Option<(&[f64], f64)>,
+    incumbent: Option<&[f64]>,
+    mut callback: Option<Callback>,
+) -> Result<(Swarm<M>, Report), String> {
+    check_bounds(&model, position_max, position_min)?;
+    // QPSO的收缩扩张系数和APSO精英学习的扰动幅度线性递减，代替惯性权重
+    let mut schedule = match options.variant {
+        Variant::Qpso => Inertia::Linear.schedule(QPSO_BETA_START, QPSO_BETA_END, c1, c2)?,
+        Variant::Apso => Inertia::Linear.schedule(APSO_SIGMA_START, APSO_SIGMA_END, c1, c2)?,
+        _ => options.inertia.schedule(w, options.w_end, c1, c2)?,
+    };
+    // 创建粒子群
+    let mut a_swarm = Swarm::new(
+        num_of_particles,
+        position_max,
+        position_min,
+        x_data,
+        y_data,
+        model,
+        options.topology,
+        options.variant,
+        options.seed,
+    );
+    if let Some((center, radius)) = warm_start {
+        a_swarm.warm_start(center, radius);
+    }
+    if let Some(position) = incumbent {
+        a_swarm.inject(position);
+    }
+    // 粒子群演化
+    let mut monitor = Monitor::new(&options.termination, position_max, position_min);
+    let mut report = Report {
+        stop_reason: StopReason::MaxSteps,
+        steps,
+        evaluations: 0,
+        restarts: 0,
+        history: History::default(),
+        uncertainty: None,
+    };
+    for step in 0..steps {
+        let (w, c1, c2) = schedule.coefficients(a_swarm.rng(), step, steps);
+        a_swarm.evolution(w, c1, c2, options.boundary);
+        let spread = if monitor.needs_spread() || options.history || callback.is_some() {
+            a_swarm.result_evaluate().1
+        } else {
+            Vec::new()
+        };
+        if options.history {
+            report.history.record(
+                a_swarm.global_best_loss,
+                &a_swarm.global_best_position,
+                a_swarm.mean_loss(),
+                &spread,
+            );
+        }
+        if let Some(callback) = callback.as_mut() {
+            let progress = Progress {
+                step: step + 1,
+                best_loss: a_swarm.global_best_loss,
+                best_position: &a_swarm.global_best_position,
+                mean_loss: a_swarm.mean_loss(),
+                spread: &spread,
+                evaluations: a_swarm.evaluations(),
+            };
+            if callback(&progress) {
+                report.stop_reason = StopReason::Callback;
+                report.steps = step + 1;
+                break;
+            }
+        }
+        if let Some(stop_reason) =
+            monitor.check(a_swarm.global_best_loss, &spread, a_swarm.evaluations())
+        {
+            report.stop_reason = stop_reason;
+            report.steps = step + 1;
+            break;
+        }
+    }
+    report.evaluations = a_swarm.evaluations();
+    Ok((a_swarm, report))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Lorentzian;
+
+    #[test]
+    fn invalid_bounds() {
+        assert!(check_bounds(&Lorentzian, &[1.0, 1.0], &[0.0, 1.0]).is_ok());
+        assert!(check_bounds(&Lorentzian, &[1.0], &[0.0]).is_err());
+        assert!(check_bounds(&Lorentzian, &[0.0, 1.0], &[1.0, 0.0]).is_err());
+        assert!(check_bounds(&Lorentzian, &[f64::NAN, 1.0], &[0.0, 0.0]).is_err());
+        assert!(check_bounds(&Lorentzian, &[f64::INFINITY, 1.0], &[0.0, 0.0]).is_err());
+        let constant = Expression::with_inferred_params("2*x").unwrap();
+        assert!(check_bounds(&constant, &[], &[]).is_err());
+    }
+
+    #[test]
+    fn nan_losses() {
+        // 负的a使sqrt(a)为NaN，第一个粒子的偏差是NaN时也不能panic
+        let model = Expression::with_inferred_params("sqrt(a)*x").unwrap();
+        let x = [1.0, 2.0, 3.0];
+        let y = [2.0, 4.0, 6.0];
+        for seed in 0..20 {
+            let options = PsoOptions {
+                seed: Some(seed),
+                ..PsoOptions::default()
+            };
+            let ((loss, best_position, _, _), _) = classic_pso(
+                &[4.0],
+                &[-4.0],
+                &x,
+                &y,
+                20,
+                0.9,
+                2.0,
+                2.0,
+                50,
+                &model,
+                &options,
+                None,
+            )
+            .unwrap();
+            assert!(loss.is_finite());
+            assert!(best_position[0] >= 0.0);
+        }
+    }
+
+    /// 洛伦兹线型 2 / (1 + x^2) 的数据
+    fn lorentzian_data() -> (Vec<f64>, Vec<f64>) {
+        let x = (0..20).map(|i| i as f64 * 0.5).collect::<Vec<f64>>();
+        let y = x.iter().map(|x| 2.0 / (1.0 + x * x)).collect();
+        (x, y)
+    }
+
+    /// 在只有给定线程数的线程池中运行
+    fn with_threads<T: Send, F: FnOnce() -> T + Send>(threads: usize, f: F) -> T {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap()
+            .install(f)
+    }
+
+    #[test]
+    fn seed_independent_of_threads() {
+        let (x, y) = lorentzian_data();
+        let settings = [
+            (
+                Variant::Classic,
+                Topology::Random,
+                Boundary::Random,
+                Inertia::Chaotic,
+            ),
+            (
+                Variant::Clpso,
+                Topology::Star,
+                Boundary::Damping,
+                Inertia::Random,
+            ),
+            (
+                Variant::Qpso,
+                Topology::VonNeumann,
+                Boundary::Reflect,
+                Inertia::Constant,
+            ),
+            (
+                Variant::Apso,
+                Topology::Ring(1),
+                Boundary::Absorb,
+                Inertia::Constant,
+            ),
+        ];
+        for (variant, topology, boundary, inertia) in settings.iter() {
+            let options = PsoOptions {
+                variant: *variant,
+                topology: *topology,
+                boundary: *boundary,
+                inertia: *inertia,
+                seed: Some(7),
+                ..PsoOptions::default()
+            };
+            let fit = || {
+                classic_pso(
+                    &[5.0, 5.0],
+                    &[0.1, 0.1],
+                    &x,
+                    &y,
+                    30,
+                    0.9,
+                    2.0,
+                    2.0,
+                    40,
+                    Lorentzian,
+                    &options,
+                    None,
+                )
+                .unwrap()
+                .0
+            };
+            let single = with_threads(1, fit);
+            assert_eq!(single, with_threads(4, fit), "{:?}", variant);
+            assert_eq!(single, fit());
+        }
+    }
+
+    #[test]
+    fn warm_start_reproducible() {
+        let (x, y) = lorentzian_data();
+        let options = PsoOptions {
+            seed: Some(11),
+            ..PsoOptions::default()
+        };
+        let warm = || {
+            let (a_swarm, report) = run(
+                &[5.0, 5.0],
+                &[0.1, 0.1],
+                &x,
+                &y,
+                20,
+                0.9,
+                2.0,
+                2.0,
+                10,
+                Lorentzian,
+                &options,
+                Some((&[2.0, 1.0], 0.05)),
+                None,
+                None,
+            )
+            .unwrap();
+            (
+                a_swarm.global_best_loss,
+                a_swarm.global_best_position,
+                report.evaluations,
+            )
+        };
+        let single = with_threads(1, warm);
+        assert_eq!(single, with_threads(4, warm));
+        // 从真值附近出发，很快就找到很好的结果
+        assert!(single.0 < 1e-3);
+    }
+
+    #[test]
+    fn sampling_independent_of_threads() {
+        let (x, y) = lorentzian_data();
+        let options = PsoOptions {
+            seed: Some(3),
+            ..PsoOptions::default()
+        };
+        let sampler = Sampler::new(50, 2.0, Some(0.01)).unwrap();
+        let sample = || {
+            let (_, chains, _) = mcmc_pso(
+                &[5.0, 5.0],
+                &[0.1, 0.1],
+                &x,
+                &y,
+                16,
+                0.9,
+                2.0,
+                2.0,
+                20,
+                Lorentzian,
+                &options,
+                &sampler,
+            )
+            .unwrap();
+            chains.chains
+        };
+        assert_eq!(with_threads(1, sample), with_threads(4, sample));
+    }
+}
@@ -0,0 +1,325 @@
+use crate::boundary::reflect;
+use crate::model::Model;
+use crate::seed::{seeded, stream};
+use crate::uncertainty::invert;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rayon::prelude::*;
+/// 估计自相关时间时窗口至少是自相关时间的这么多倍
+const AUTOCORRELATION_WINDOW: f64 = 5.0;
+/// 行走者初始扰动的标准差最多是这个维度的范围的这么多倍
+const JITTER_SCALE: f64 = 1e-3;
+/// 仿射不变系综采样（stretch move）的设置
+#[derive(Debug, Clone, Copy)]
+pub struct Sampler {
+    /// 每个行走者走的步数
+    pub steps: usize,
+    /// 伸缩因子z在 [1/a, a] 之间按 g(z) ∝ 1/sqrt(z) 分布，a是这里的伸缩系数
+    pub stretch: f64,
+    /// 高斯似然中数据的噪声方差，为None时用最佳拟合的残差方差
+    pub noise_variance: Option<f64>,
+}
+impl Sampler {
+    /// 检查设置，伸缩系数必须大于1，噪声方差必须是正数
+    pub fn new(steps: usize, stretch: f64, noise_variance: Option<f64>) -> Result<Self, String> {
+        if !(stretch > 1.0 && stretch.is_finite()) {
+            return Err(format!(
+                "stretch scale must be greater than 1, but got {}",
+                stretch
+            ));
+        }
+        if let Some(variance) = noise_variance {
+            if !(variance > 0.0 && variance.is_finite()) {
+                return Err(format!(
+                    "noise variance must be positive, but got {}",
+                    variance
+                ));
+            }
+        }
+        Ok(Sampler {
+            steps,
+            stretch,
+            noise_variance,
+        })
+    }
+}
+/// 采样的结果
+#[derive(Debug, Clone)]
+pub struct Chains {
+    /// 每个行走者每一步的位置
+    pub chains: Vec<Vec<Vec<f64>>>,
+    /// 每个行走者接受提议的比例
+    pub acceptance_fractions: Vec<f64>,
+    /// 每个参数的积分自相关时间，以步数为单位
+    pub autocorrelation_times: Vec<f64>,
+}
+/// 以给出的位置为中心加上小的高斯扰动作为行走者，扰动后反射回上下限内。
+/// 扰动的标准差是参数的标准误差，但不超过范围的千分之一，标准误差不是有限正数时取范围的千分之一。
+/// 行走者至少要有参数个数的两倍，并且在范围不为零的维度上不能落在更低维的子空间里，
+/// 否则伸缩移动永远走不出这个子空间
+pub fn initialize_walkers<R: Rng>(
+    rng: &mut R,
+    positions: &[Vec<f64>],
+    position_max: &[f64],
+    position_min: &[f64],
+    standard_errors: &[f64],
+) -> Result<Vec<Vec<f64>>, String> {
+    let dims = position_max.len();
+    if positions.len() < 2 * dims {
+        return Err(format!(
+            "ensemble sampling needs at least {} walkers for {} parameters, but got {}",
+            2 * dims,
+            dims,
+            positions.len()
+        ));
+    }
+    let walkers = positions
+        .iter()
+        .map(|position| {
+            (0..dims)
+                .map(|j| {
+                    let range = position_max[j] - position_min[j];
+                    let sigma = match standard_errors.get(j) {
+                        Some(se) if se.is_finite() && *se > 0.0 => se.min(range * JITTER_SCALE),
+                        _ => range * JITTER_SCALE,
+                    };
+                    // Box-Muller变换得到标准正态分布的随机数
+                    let u1 = 1.0 - rng.gen::<f64>();
+                    let u2 = rng.gen::<f64>();
+                    let gaussian =
+                        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+                    reflect(
+                        position[j] + sigma * gaussian,
+                        position_max[j],
+                        position_min[j],
+                    )
+                })
+                .collect::<Vec<f64>>()
+        })
+        .collect::<Vec<Vec<f64>>>();
+    if is_degenerate(&walkers, position_max, position_min) {
+        return Err(
+            "walkers lie in a lower-dimensional subspace, the posterior cannot be sampled"
+                .to_string(),
+        );
+    }
+    Ok(walkers)
+}
+/// 行走者在范围不为零的维度上的相关系数矩阵奇异时，它们落在更低维的子空间里
+fn is_degenerate(walkers: &[Vec<f64>], position_max: &[f64], position_min: &[f64]) -> bool {
+    let free = (0..position_max.len())
+        .filter(|j| position_max[*j] > position_min[*j])
+        .collect::<Vec<usize>>();
+    let n = walkers.len() as f64;
+    let centered = free
+        .iter()
+        .map(|j| {
+            let mean = walkers.iter().map(|w| w[*j]).sum::<f64>() / n;
+            walkers.iter().map(|w| w[*j] - mean).collect()
+        })
+        .collect::<Vec<Vec<f64>>>();
+    let deviations = centered
+        .iter()
+        .map(|c| c.iter().map(|d| d * d).sum::<f64>().sqrt())
+        .collect::<Vec<f64>>();
+    if deviations.iter().any(|d| !(*d > 0.0 && d.is_finite())) {
+        return true;
+    }
+    let correlation = (0..free.len())
+        .map(|a| {
+            (0..free.len())
+                .map(|b| {
+                    centered[a]
+                        .iter()
+                        .zip(&centered[b])
+                        .map(|(x, y)| x * y)
+                        .sum::<f64>()
+                        / (deviations[a] * deviations[b])
+                })
+                .collect()
+        })
+        .collect::<Vec<Vec<f64>>>();
+    invert(&correlation).is_none()
+}
+/// 以高斯似然 exp(-平方偏差 / (2 * 噪声方差)) 和位置上下限内均匀的先验，
+/// 从给出的行走者出发用仿射不变系综采样后验分布。
+/// 行走者分成两半轮流更新，每一半内并行，每个行走者各用一个随机数流，结果与线程数无关
+#[allow(clippy::too_many_arguments)]
+pub fn sample<M: Model>(
+    model: &M,
+    walkers: Vec<Vec<f64>>,
+    position_max: &[f64],
+    position_min: &[f64],
+    x_data: &[f64],
+    y_data: &[f64],
+    noise_variance: f64,
+    sampler: &Sampler,
+    seed: Option<u64>,
+) -> Chains {
+    let num_of_walkers = walkers.len();
+    let dims = position_max.len();
+    let log_probability = |position: &[f64]| {
+        let inside = position
+            .iter()
+            .zip(position_max)
+            .zip(position_min)
+            .all(|((p, max), min)| p <= max && p >= min);
+        if inside {
+            -model.calc_loss(position, x_data, y_data) / (2.0 * noise_variance)
+        } else {
+            f64::NEG_INFINITY
+        }
+    };
+    let mut rng = seeded(seed);
+    let mut walker_rngs = (0..num_of_walkers)
+        .map(|_| stream(&mut rng))
+        .collect::<Vec<StdRng>>();
+    let mut log_probabilities = walkers
+        .par_iter()
+        .map(|w| log_probability(w))
+        .collect::<Vec<f64>>();
+    let mut positions = walkers;
+    let mut accepted = vec![0usize; num_of_walkers];
+    let mut chains = vec![Vec::with_capacity(sampler.steps); num_of_walkers];
+    let half = num_of_walkers / 2;
+    let a = sampler.stretch;
+    for _ in 0..sampler.steps {
+        // 前一半行走者向后一半学习，然后反过来
+        for (active, complement) in [
+            (0..half, half..num_of_walkers),
+            (half..num_of_walkers, 0..half),
+        ]
+        .iter()
+        {
+            if complement.is_empty() {
+                continue;
+            }
+            let others = &positions[complement.clone()];
+            let moves = walker_rngs[active.clone()]
+                .par_iter_mut()
+                .zip(&positions[active.clone()])
+                .zip(&log_probabilities[active.clone()])
+                .map(|((rng, position), log_p)| {
+                    let other = &others[rng.gen_range(0, others.len())];
+                    // z = ((a - 1) u + 1)^2 / a
+                    let z = ((a - 1.0) * rng.gen::<f64>() + 1.0).powi(2) / a;
+                    let proposal = other
+                        .iter()
+                        .zip(position)
+                        .map(|(o, p)| o + z * (p - o))
+                        .collect::<Vec<f64>>();
+                    let proposal_log_p = log_probability(&proposal);
+                    let log_ratio = (dims as f64 - 1.0) * z.ln() + proposal_log_p - log_p;
+                    if proposal_log_p.is_finite() && rng.gen::<f64>().ln() < log_ratio {
+                        Some((proposal, proposal_log_p))
+                    } else {
+                        None
+                    }
+                })
+                .collect::<Vec<Option<(Vec<f64>, f64)>>>();
+            for (k, m) in active.clone().zip(moves) {
+                if let Some((proposal, proposal_log_p)) = m {
+                    positions[k] = proposal;
+                    log_probabilities[k] = proposal_log_p;
+                    accepted[k] += 1;
+                }
+            }
+        }
+        for (chain, position) in chains.iter_mut().zip(&positions) {
+            chain.push(position.clone());
+        }
+    }
+    let acceptance_fractions = accepted
+        .iter()
+        .map(|a| *a as f64 / sampler.steps.max(1) as f64)
+        .collect();
+    let autocorrelation_times = (0..dims)
+        .map(|j| autocorrelation_time(&chains, j))
+        .collect();
+    Chains {
+        chains,
+        acceptance_fractions,
+        autocorrelation_times,
+    }
+}
+/// 参数j的积分自相关时间 tau = 1 + 2 Σ rho(t)，rho是各行走者的自相关函数的平均，
+/// 求和到窗口不小于自相关时间的5倍为止
+fn autocorrelation_time(chains: &[Vec<Vec<f64>>], j: usize) -> f64 {
+    let steps = chains.first().map_or(0, |c| c.len());
+    if steps < 2 {
+        return f64::NAN;
+    }
+    let series = chains
+        .iter()
+        .map(|chain| {
+            let values = chain.iter().map(|p| p[j]).collect::<Vec<f64>>();
+            let mean = values.iter().sum::<f64>() / steps as f64;
+            values.iter().map(|v| v - mean).collect::<Vec<f64>>()
+        })
+        .collect::<Vec<Vec<f64>>>();
+    let autocovariance = |lag: usize| {
+        series
+            .iter()
+            .map(|s| s.iter().zip(&s[lag..]).map(|(a, b)| a * b).sum::<f64>() / steps as f64)
+            .sum::<f64>()
+            / series.len() as f64
+    };
+    let variance = autocovariance(0);
+    if variance <= 0.0 {
+        return f64::NAN;
+    }
+    let mut tau = 1.0;
+    for lag in 1..steps {
+        tau += 2.0 * autocovariance(lag) / variance;
+        if lag as f64 >= AUTOCORRELATION_WINDOW * tau {
+            break;
+        }
+    }
+    tau
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walkers_are_spread() {
+        let mut rng = seeded(Some(1));
+        // 所有粒子收敛到同一点，其中一维在上限上
+        let positions = vec![vec![1.0, 0.5]; 8];
+        let walkers = initialize_walkers(
+            &mut rng,
+            &positions,
+            &[1.0, 1.0],
+            &[0.0, 0.0],
+            &[f64::INFINITY, 1e-6],
+        )
+        .unwrap();
+        assert!(!is_degenerate(&walkers, &[1.0, 1.0], &[0.0, 0.0]));
+        for w in &walkers {
+            assert!(w[0] <= 1.0 && w[0] > 0.99);
+            assert!((w[1] - 0.5).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn too_few_walkers() {
+        let mut rng = seeded(Some(1));
+        let positions = vec![vec![0.5, 0.5]; 3];
+        assert!(initialize_walkers(&mut rng, &positions, &[1.0, 1.0], &[0.0, 0.0], &[]).is_err());
+    }
+
+    #[test]
+    fn degenerate_walkers() {
+        // 行走者都在直线 b = a 上
+        let walkers = (0..6)
+            .map(|i| vec![i as f64 / 10.0, i as f64 / 10.0])
+            .collect::<Vec<Vec<f64>>>();
+        assert!(is_degenerate(&walkers, &[1.0, 1.0], &[0.0, 0.0]));
+        // 范围为零的维度不计入
+        let fixed = (0..6)
+            .map(|i| vec![i as f64 / 10.0, 0.5])
+            .collect::<Vec<Vec<f64>>>();
+        assert!(!is_degenerate(&fixed, &[1.0, 0.5], &[0.0, 0.5]));
+        assert!(is_degenerate(&fixed, &[1.0, 1.0], &[0.0, 0.0]));
+    }
+}
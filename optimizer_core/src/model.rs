@@ -0,0 +1,157 @@
+/// 待拟合的模型
+pub trait Model: Sync {
+    /// 模型参数的个数
+    fn num_of_params(&self) -> usize;
+    /// 模型参数的名字，顺序与粒子位置的各个维度一致
+    fn param_names(&self) -> Vec<String>;
+    /// 由自变量x和参数算出模型的预测值
+    fn eval(&self, x: f64, params: &[f64]) -> f64;
+    /// 模型在x处对各个参数的解析梯度，没有解析式的模型返回None
+    fn gradient(&self, _x: f64, _params: &[f64]) -> Option<Vec<f64>> {
+        None
+    }
+    /// 由参数和待拟合数据算出平方偏差
+    fn calc_loss(&self, params: &[f64], x_data: &[f64], y_data: &[f64]) -> f64 {
+        // x带入函数得到预测y，计算预测y和实际y的平方偏差，最后每个数据点的偏差相加
+        x_data
+            .iter()
+            .map(|x| self.eval(*x, params))
+            .zip(y_data)
+            .map(|(y_pred, y_data)| (y_pred - y_data).powi(2))
+            .sum()
+    }
+}
+/// 把NaN偏差当作无穷大，使偏差之间总能比较大小。用户给出的模型在参数越出定义域时可能得到NaN
+pub fn comparable_loss(loss: f64) -> f64 {
+    if loss.is_nan() {
+        f64::INFINITY
+    } else {
+        loss
+    }
+}
+/// 按名字取出内置的模型，可选 "biexponential"、"fano" 和 "lorentzian"
+pub fn builtin(name: &str) -> Option<Box<dyn Model>> {
+    match name {
+        "biexponential" => Some(Box::new(Biexponential)),
+        "fano" => Some(Box::new(Fano)),
+        "lorentzian" => Some(Box::new(Lorentzian)),
+        _ => None,
+    }
+}
+/// 模型的引用也可以作为模型使用，以便在优化结束后继续访问模型
+impl<M: Model + ?Sized> Model for &M {
+    fn num_of_params(&self) -> usize {
+        (**self).num_of_params()
+    }
+    fn param_names(&self) -> Vec<String> {
+        (**self).param_names()
+    }
+    fn eval(&self, x: f64, params: &[f64]) -> f64 {
+        (**self).eval(x, params)
+    }
+    fn gradient(&self, x: f64, params: &[f64]) -> Option<Vec<f64>> {
+        (**self).gradient(x, params)
+    }
+    fn calc_loss(&self, params: &[f64], x_data: &[f64], y_data: &[f64]) -> f64 {
+        (**self).calc_loss(params, x_data, y_data)
+    }
+}
+/// 双指数衰减 y = a1 * exp(-x / e1) + a2 * exp(-x / e2)
+#[derive(Debug, Clone, Copy)]
+pub struct Biexponential;
+impl Model for Biexponential {
+    fn num_of_params(&self) -> usize {
+        4
+    }
+    fn param_names(&self) -> Vec<String> {
+        vec!["a1".into(), "a2".into(), "e1".into(), "e2".into()]
+    }
+    fn eval(&self, x: f64, params: &[f64]) -> f64 {
+        // 取出参数
+        let a1 = params[0];
+        let a2 = params[1];
+        let e1 = params[2];
+        let e2 = params[3];
+        (-x / e1).exp() * a1 + (-x / e2).exp() * a2
+    }
+    fn gradient(&self, x: f64, params: &[f64]) -> Option<Vec<f64>> {
+        let a1 = params[0];
+        let a2 = params[1];
+        let e1 = params[2];
+        let e2 = params[3];
+        let exp1 = (-x / e1).exp();
+        let exp2 = (-x / e2).exp();
+        Some(vec![
+            exp1,
+            exp2,
+            a1 * exp1 * x / e1.powi(2),
+            a2 * exp2 * x / e2.powi(2),
+        ])
+    }
+}
+/// BWF（Fano）线型 y = i0 * (1 + s / q_bwf)^2 / (1 + s^2)，其中 s = (x - omega_g) / gamma
+#[derive(Debug, Clone, Copy)]
+pub struct Fano;
+impl Model for Fano {
+    fn num_of_params(&self) -> usize {
+        4
+    }
+    fn param_names(&self) -> Vec<String> {
+        vec![
+            "omega_g".into(),
+            "q_bwf".into(),
+            "gamma".into(),
+            "i0".into(),
+        ]
+    }
+    fn eval(&self, x: f64, params: &[f64]) -> f64 {
+        // 取出参数
+        let omega_g = params[0];
+        let q_bwf = params[1];
+        let gamma = params[2];
+        let i0 = params[3];
+        let s = (x - omega_g) / gamma;
+        i0 * (1.0 + s / q_bwf).powi(2) / (1.0 + s.powi(2))
+    }
+    fn gradient(&self, x: f64, params: &[f64]) -> Option<Vec<f64>> {
+        let omega_g = params[0];
+        let q_bwf = params[1];
+        let gamma = params[2];
+        let i0 = params[3];
+        let s = (x - omega_g) / gamma;
+        let numerator = (1.0 + s / q_bwf).powi(2);
+        let denominator = 1.0 + s.powi(2);
+        // 先求出对s的导数，再由 ds/domega_g = -1/gamma，ds/dgamma = -s/gamma 链式求导
+        let dy_ds = i0 * (2.0 * (1.0 + s / q_bwf) / q_bwf * denominator - numerator * 2.0 * s)
+            / denominator.powi(2);
+        Some(vec![
+            -dy_ds / gamma,
+            i0 * 2.0 * (1.0 + s / q_bwf) * (-s / q_bwf.powi(2)) / denominator,
+            -dy_ds * s / gamma,
+            numerator / denominator,
+        ])
+    }
+}
+/// 洛伦兹线型 y = a / (b + x^2)
+#[derive(Debug, Clone, Copy)]
+pub struct Lorentzian;
+impl Model for Lorentzian {
+    fn num_of_params(&self) -> usize {
+        2
+    }
+    fn param_names(&self) -> Vec<String> {
+        vec!["a".into(), "b".into()]
+    }
+    fn eval(&self, x: f64, params: &[f64]) -> f64 {
+        // 取出参数
+        let a = params[0];
+        let b = params[1];
+        a / (b + x.powi(2))
+    }
+    fn gradient(&self, x: f64, params: &[f64]) -> Option<Vec<f64>> {
+        let a = params[0];
+        let b = params[1];
+        let denominator = b + x.powi(2);
+        Some(vec![1.0 / denominator, -a / denominator.powi(2)])
+    }
+}
@@ -0,0 +1,256 @@
+use crate::model::Model;
+/// 剖面似然的设置
+#[derive(Debug, Clone, Copy)]
+pub struct Profile {
+    /// 每个参数在上下限之间均匀取的格点数，包括上下限
+    pub points: usize,
+    /// 似然比置信区间的置信水平
+    pub confidence: f64,
+    /// 重新拟合时初始粒子在最佳位置附近的范围，以各维度上下限之间的距离为单位
+    pub warm_radius: f64,
+    /// 高斯似然中数据的噪声方差，为None时用最佳拟合的残差方差
+    pub noise_variance: Option<f64>,
+}
+impl Profile {
+    /// 检查设置，格点至少两个，置信水平在0和1之间，初始范围和噪声方差是正数
+    pub fn new(
+        points: usize,
+        confidence: f64,
+        warm_radius: f64,
+        noise_variance: Option<f64>,
+    ) -> Result<Self, String> {
+        if points < 2 {
+            return Err(format!(
+                "profile needs at least 2 grid points, but got {}",
+                points
+            ));
+        }
+        if !(confidence > 0.0 && confidence < 1.0) {
+            return Err(format!(
+                "confidence must be between 0 and 1, but got {}",
+                confidence
+            ));
+        }
+        if !(warm_radius > 0.0 && warm_radius.is_finite()) {
+            return Err(format!(
+                "warm start radius must be positive, but got {}",
+                warm_radius
+            ));
+        }
+        if let Some(variance) = noise_variance {
+            if !(variance > 0.0 && variance.is_finite()) {
+                return Err(format!(
+                    "noise variance must be positive, but got {}",
+                    variance
+                ));
+            }
+        }
+        Ok(Profile {
+            points,
+            confidence,
+            warm_radius,
+            noise_variance,
+        })
+    }
+    /// 第i个参数的格点
+    pub fn grid(&self, position_max: &[f64], position_min: &[f64], i: usize) -> Vec<f64> {
+        let step = (position_max[i] - position_min[i]) / (self.points - 1) as f64;
+        (0..self.points)
+            .map(|k| position_min[i] + step * k as f64)
+            .collect()
+    }
+}
+/// 剖面似然的结果
+#[derive(Debug, Clone)]
+pub struct ProfileResult {
+    /// 每个参数的格点
+    pub grids: Vec<Vec<f64>>,
+    /// 每个参数固定在各个格点上、重新拟合其余参数得到的最小偏差
+    pub losses: Vec<Vec<f64>>,
+    /// 每个参数的似然比置信区间的下限，偏差曲线在格点范围内没有越过阈值时取下限
+    pub lower: Vec<f64>,
+    /// 每个参数的似然比置信区间的上限，偏差曲线在格点范围内没有越过阈值时取上限
+    pub upper: Vec<f64>,
+}
+impl ProfileResult {
+    /// 由剖面偏差曲线算出似然比置信区间。高斯似然下 2 Δ(对数似然) = Δ(平方偏差) / 噪声方差，
+    /// 区间是偏差不超过最小偏差加上 噪声方差 * 自由度为1的卡方分布分位数 的范围，
+    /// 最佳位置也作为曲线上的一点，越过阈值的位置在相邻两点之间线性插值
+    pub fn new(
+        grids: Vec<Vec<f64>>,
+        losses: Vec<Vec<f64>>,
+        best_position: &[f64],
+        best_loss: f64,
+        noise_variance: f64,
+        confidence: f64,
+    ) -> Self {
+        let z = normal_quantile((1.0 + confidence) / 2.0);
+        let (lower, upper) = grids
+            .iter()
+            .zip(&losses)
+            .zip(best_position)
+            .map(|((grid, losses), best)| {
+                let mut curve = grid
+                    .iter()
+                    .cloned()
+                    .zip(losses.iter().cloned())
+                    .filter(|(_, loss)| !loss.is_nan())
+                    .collect::<Vec<(f64, f64)>>();
+                curve.push((*best, best_loss));
+                curve.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+                let minimum = curve
+                    .iter()
+                    .enumerate()
+                    .min_by(|a, b| {
+                        (a.1)
+                            .1
+                            .partial_cmp(&(b.1).1)
+                            .unwrap_or(std::cmp::Ordering::Equal)
+                    })
+                    .map(|(k, _)| k)
+                    .unwrap();
+                let threshold = curve[minimum].1 + noise_variance * z * z;
+                let crossing = |inside: (f64, f64), outside: (f64, f64)| {
+                    inside.0
+                        + (threshold - inside.1) / (outside.1 - inside.1) * (outside.0 - inside.0)
+                };
+                let lower = (0..minimum)
+                    .rev()
+                    .find(|k| curve[*k].1 > threshold)
+                    .map_or(grid[0], |k| crossing(curve[k + 1], curve[k]));
+                let upper = (minimum + 1..curve.len())
+                    .find(|k| curve[*k].1 > threshold)
+                    .map_or(grid[grid.len() - 1], |k| crossing(curve[k - 1], curve[k]));
+                (lower, upper)
+            })
+            .unzip();
+        ProfileResult {
+            grids,
+            losses,
+            lower,
+            upper,
+        }
+    }
+}
+/// 把一个参数固定在给定值上的模型，参数个数少一个
+pub struct Fixed<M: Model> {
+    /// 原来的模型
+    pub model: M,
+    /// 固定的参数的序号
+    pub index: usize,
+    /// 固定的参数的值
+    pub value: f64,
+}
+impl<M: Model> Fixed<M> {
+    /// 在其余参数中插入固定的参数，得到原来模型的参数
+    pub fn full_params(&self, params: &[f64]) -> Vec<f64> {
+        let mut full = params.to_vec();
+        full.insert(self.index, self.value);
+        full
+    }
+}
+impl<M: Model> Model for Fixed<M> {
+    fn num_of_params(&self) -> usize {
+        self.model.num_of_params() - 1
+    }
+    fn param_names(&self) -> Vec<String> {
+        let mut names = self.model.param_names();
+        names.remove(self.index);
+        names
+    }
+    fn eval(&self, x: f64, params: &[f64]) -> f64 {
+        self.model.eval(x, &self.full_params(params))
+    }
+    fn gradient(&self, x: f64, params: &[f64]) -> Option<Vec<f64>> {
+        let mut gradient = self.model.gradient(x, &self.full_params(params))?;
+        gradient.remove(self.index);
+        Some(gradient)
+    }
+    fn calc_loss(&self, params: &[f64], x_data: &[f64], y_data: &[f64]) -> f64 {
+        self.model
+            .calc_loss(&self.full_params(params), x_data, y_data)
+    }
+}
+/// 标准正态分布的分位数，用Acklam的有理函数近似，相对误差约1e-9
+fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969683028665376e1,
+        2.209460984245205e2,
+        -2.759285104469687e2,
+        1.38357751867269e2,
+        -3.066479806614716e1,
+        2.506628277459239,
+    ];
+    const B: [f64; 5] = [
+        -5.447609879822406e1,
+        1.615858368580409e2,
+        -1.556989798598866e2,
+        6.680131188771972e1,
+        -1.328068155288572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784894002430293e-3,
+        -3.223964580411365e-1,
+        -2.400758277161838,
+        -2.549732539343734,
+        4.374664141464968,
+        2.938163982698783,
+    ];
+    const D: [f64; 4] = [
+        7.784695709041462e-3,
+        3.224671290700398e-1,
+        2.445134137142996,
+        3.754408661907416,
+    ];
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+    if p < 0.02425 {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - 0.02425 {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantiles() {
+        assert!((normal_quantile(0.975) - 1.959_963_985).abs() < 1e-6);
+        assert!((normal_quantile(0.5)).abs() < 1e-12);
+        assert!((normal_quantile(0.001) + 3.090_232_306).abs() < 1e-6);
+        assert!((normal_quantile(0.999) - 3.090_232_306).abs() < 1e-6);
+    }
+
+    #[test]
+    fn likelihood_ratio_interval() {
+        // 抛物线偏差曲线的区间是 最佳值 ± z * 噪声标准差
+        let profile = Profile::new(2001, 0.95, 0.1, None).unwrap();
+        let grid = profile.grid(&[6.0], &[-4.0], 0);
+        let losses = grid.iter().map(|v| 4.0 * (v - 1.0).powi(2)).collect();
+        let result = ProfileResult::new(vec![grid], vec![losses], &[1.0], 0.0, 1.0, 0.95);
+        assert!((result.lower[0] - (1.0 - 1.959_964 / 2.0)).abs() < 1e-3);
+        assert!((result.upper[0] - (1.0 + 1.959_964 / 2.0)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn interval_reaches_bounds() {
+        let grid = vec![0.0, 1.0, 2.0];
+        let result = ProfileResult::new(
+            vec![grid],
+            vec![vec![0.0, 0.1, 0.2]],
+            &[0.0],
+            0.0,
+            1.0,
+            0.95,
+        );
+        assert_eq!((result.lower[0], result.upper[0]), (0.0, 2.0));
+    }
+}
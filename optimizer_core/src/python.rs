@@ -0,0 +1,817 @@
+use crate::model::Model;
+use crate::termination::{time_limit_from_secs, Progress, Report, Termination};
+use crate::{Boundary, Inertia, PsoOptions, Restart, Topology, Variant};
+use pyo3::exceptions::ValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyTuple};
+use std::sync::Mutex;
+/// 以Python函数 f(x, *params) 为模型，x 以numpy数组的形式整体传入
+#[derive(Debug)]
+pub struct PyModel {
+    /// Python函数
+    func: PyObject,
+    /// numpy模块，用于把待拟合数据转换为numpy数组
+    numpy: PyObject,
+    /// 参数的名字
+    param_names: Vec<String>,
+    /// Python函数第一次抛出的异常，出错后不再调用Python函数
+    error: Mutex<Option<PyObject>>,
+}
+impl PyModel {
+    /// 由Python函数和参数的名字构建模型
+    pub fn new(py: Python, func: PyObject, param_names: Vec<String>) -> PyResult<Self> {
+        let numpy = py.import("numpy")?.to_object(py);
+        Ok(PyModel {
+            func,
+            numpy,
+            param_names,
+            error: Mutex::new(None),
+        })
+    }
+    /// 取出Python函数在优化过程中抛出的异常
+    pub fn take_error(&self, py: Python) -> PyResult<()> {
+        match self.error.lock().unwrap().take() {
+            Some(err) => Err(PyErr::from_instance(err.as_ref(py))),
+            None => Ok(()),
+        }
+    }
+    /// 是否已经出过错
+    fn failed(&self) -> bool {
+        self.error.lock().unwrap().is_some()
+    }
+    /// 记录第一次出现的异常
+    fn record_error(&self, py: Python, err: PyErr) {
+        let mut error = self.error.lock().unwrap();
+        if error.is_none() {
+            *error = Some(err.to_object(py));
+        }
+    }
+    /// 以 f(x, *params) 的形式调用Python函数
+    fn call(&self, py: Python, x: PyObject, params: &[f64]) -> PyResult<PyObject> {
+        let args = std::iter::once(x)
+            .chain(params.iter().map(|p| p.to_object(py)))
+            .collect::<Vec<PyObject>>();
+        self.func.call1(py, PyTuple::new(py, args))
+    }
+    /// 以整个数据集为自变量调用Python函数，得到每个数据点的预测值
+    fn predict(&self, py: Python, params: &[f64], x_data: &[f64]) -> PyResult<Vec<f64>> {
+        let x = self
+            .numpy
+            .call_method1(py, "asarray", (x_data.to_object(py),))?;
+        let y_pred = self
+            .numpy
+            .call_method1(py, "asarray", (self.call(py, x, params)?, "float64"))?
+            .extract::<Vec<f64>>(py)?;
+        if y_pred.len() != x_data.len() {
+            return Err(ValueError::py_err(format!(
+                "model returned {} values for {} data points",
+                y_pred.len(),
+                x_data.len()
+            )));
+        }
+        Ok(y_pred)
+    }
+}
+impl Model for PyModel {
+    fn num_of_params(&self) -> usize {
+        self.param_names.len()
+    }
+    fn param_names(&self) -> Vec<String> {
+        self.param_names.clone()
+    }
+    fn eval(&self, x: f64, params: &[f64]) -> f64 {
+        if self.failed() {
+            return f64::NAN;
+        }
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        match self
+            .call(py, x.to_object(py), params)
+            .and_then(|y| y.extract::<f64>(py))
+        {
+            Ok(y) => y,
+            Err(err) => {
+                self.record_error(py, err);
+                f64::NAN
+            }
+        }
+    }
+    fn calc_loss(&self, params: &[f64], x_data: &[f64], y_data: &[f64]) -> f64 {
+        // 出错之后的偏差视为无穷大，粒子不会再接受这个位置
+        if self.failed() {
+            return f64::INFINITY;
+        }
+        // 每个粒子调用一次Python函数，调用期间持有GIL
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        match self.predict(py, params, x_data) {
+            Ok(y_pred) => y_pred
+                .iter()
+                .zip(y_data)
+                .map(|(y_pred, y_data)| (y_pred - y_data).powi(2))
+                .sum(),
+            Err(err) => {
+                self.record_error(py, err);
+                f64::INFINITY
+            }
+        }
+    }
+}
+/// 以Python函数 callback(step, best_loss, best_position, statistics) 为回调函数，
+/// statistics是包含平均偏差、位置的标准差和计算偏差次数的字典，返回真值时提前停止
+#[derive(Debug)]
+pub struct PyCallback {
+    /// Python函数
+    func: PyObject,
+    /// Python函数抛出的异常，出错后停止优化
+    error: Option<PyObject>,
+}
+impl PyCallback {
+    pub fn new(func: PyObject) -> Self {
+        PyCallback { func, error: None }
+    }
+    /// 调用Python函数，返回是否应当停止
+    pub fn observe(&mut self, progress: &Progress) -> bool {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        match self.call(py, progress) {
+            Ok(stop) => stop,
+            Err(err) => {
+                self.error = Some(err.to_object(py));
+                true
+            }
+        }
+    }
+    /// 取出Python函数在优化过程中抛出的异常
+    pub fn take_error(&mut self, py: Python) -> PyResult<()> {
+        match self.error.take() {
+            Some(err) => Err(PyErr::from_instance(err.as_ref(py))),
+            None => Ok(()),
+        }
+    }
+    fn call(&self, py: Python, progress: &Progress) -> PyResult<bool> {
+        let statistics = PyDict::new(py);
+        statistics.set_item("mean_loss", progress.mean_loss)?;
+        statistics.set_item("spread", progress.spread)?;
+        statistics.set_item("evaluations", progress.evaluations)?;
+        let args = vec![
+            progress.step.to_object(py),
+            progress.best_loss.to_object(py),
+            progress.best_position.to_object(py),
+            statistics.to_object(py),
+        ];
+        self.func.call1(py, PyTuple::new(py, args))?.is_true(py)
+    }
+}
+/// 按full_output决定是否在结果后面附上优化过程的概况
+///
+/// 结果是元组时把概况字典追加为最后一个元素，否则返回 (结果, 概况字典)
+pub fn with_report<T: ToPyObject>(
+    py: Python,
+    result: T,
+    report: &Report,
+    full_output: bool,
+) -> PyResult<PyObject> {
+    let result = result.to_object(py);
+    if !full_output {
+        return Ok(result);
+    }
+    let info = PyDict::new(py);
+    info.set_item("stop_reason", report.stop_reason.name())?;
+    info.set_item("steps", report.steps)?;
+    info.set_item("evaluations", report.evaluations)?;
+    info.set_item("restarts", report.restarts)?;
+    if !report.history.is_empty() {
+        let history = PyDict::new(py);
+        history.set_item("best_loss", &report.history.best_loss)?;
+        history.set_item("best_position", &report.history.best_position)?;
+        history.set_item("mean_loss", &report.history.mean_loss)?;
+        history.set_item("spread", &report.history.spread)?;
+        info.set_item("history", history)?;
+    }
+    if let Some(uncertainty) = &report.uncertainty {
+        info.set_item("standard_errors", &uncertainty.standard_errors)?;
+        info.set_item("covariance", &uncertainty.covariance)?;
+        info.set_item("correlation", &uncertainty.correlation)?;
+        info.set_item("residual_variance", uncertainty.residual_variance)?;
+    }
+    let mut items: Vec<PyObject> = match result.cast_as::<PyTuple>(py) {
+        Ok(tuple) => tuple.iter().map(|x| x.to_object(py)).collect(),
+        Err(_) => vec![result],
+    };
+    items.push(info.to_object(py));
+    Ok(PyTuple::new(py, items).to_object(py))
+}
+/// 由Python函数的关键字参数构建提前终止的条件，time_limit以秒为单位
+pub fn termination(
+    stall_steps: Option<usize>,
+    loss_tolerance: f64,
+    relative_tolerance: f64,
+    position_tolerance: Option<f64>,
+    max_evaluations: Option<usize>,
+    time_limit: Option<f64>,
+) -> PyResult<Termination> {
+    let time_limit = time_limit
+        .map(time_limit_from_secs)
+        .transpose()
+        .map_err(ValueError::py_err)?;
+    Termination::new(
+        stall_steps,
+        loss_tolerance,
+        relative_tolerance,
+        position_tolerance,
+        max_evaluations,
+        time_limit,
+    )
+    .map_err(ValueError::py_err)
+}
+/// restarts为true时按population_growth构建重启的设置，否则不重启
+pub fn restart(restarts: bool, population_growth: f64) -> PyResult<Option<Restart>> {
+    if restarts {
+        Ok(Some(
+            Restart::new(population_growth).map_err(ValueError::py_err)?,
+        ))
+    } else {
+        Ok(None)
+    }
+}
+/// 由Python函数的关键字参数构建粒子群优化的设置，不重启，不记录历史和不确定度
+#[allow(clippy::too_many_arguments)]
+pub fn pso_options(
+    boundary: &str,
+    inertia: &str,
+    w_end: f64,
+    topology: &str,
+    radius: usize,
+    variant: &str,
+    termination: Termination,
+    seed: Option<u64>,
+) -> PyResult<PsoOptions> {
+    Ok(PsoOptions {
+        boundary: Boundary::from_name(boundary).map_err(ValueError::py_err)?,
+        inertia: Inertia::from_name(inertia).map_err(ValueError::py_err)?,
+        w_end,
+        topology: Topology::from_name(topology, radius).map_err(ValueError::py_err)?,
+        variant: Variant::from_name(variant).map_err(ValueError::py_err)?,
+        termination,
+        seed,
+        ..PsoOptions::default()
+    })
+}
+/// 为内置模型生成导出给Python的粒子群优化函数 classic_pso、niching_pso、bootstrap_pso、
+/// mcmc_pso 和 profile_pso，放在模块pso_functions中，用 pso_functions::register 加入Python模块
+#[macro_export]
+macro_rules! pso_functions {
+    ($model:path) => {
+        mod pso_functions {
+            use pyo3::exceptions::ValueError;
+            use pyo3::prelude::*;
+            use pyo3::wrap_pyfunction;
+            use $crate::python::{pso_options, restart, termination, with_report, PyCallback};
+            use $crate::{Bootstrap, Callback, Profile, Progress, PsoOptions, Resampling, Sampler};
+            /// 导出的粒子群优化函数，越界的处理方式可选 "absorb"、"reflect"、"periodic"、"random" 和 "damping"，
+            /// 惯性权重可选 "constant"、"linear"、"nonlinear"、"chaotic"、"random" 和 "constriction"，
+            /// 递减的惯性权重从w变化到w_end。
+            /// 拓扑结构可选 "star"、"ring"、"von-neumann" 和 "random"，radius是环形拓扑的邻居半径。
+            /// 变体可选 "classic"、"clpso"、"qpso" 和 "apso"，QPSO不使用w、c1和c2，APSO自动调整它们。
+            /// stall_steps、loss_tolerance、relative_tolerance、position_tolerance、max_evaluations
+            /// 和time_limit（秒）是提前终止的条件，full_output为True时在结果后面附上包含停止原因、
+            /// 实际代数、计算偏差次数和重启次数的字典。
+            /// restarts为True时停滞后重新初始化种群并放入之前最好的结果，粒子数乘以population_growth，
+            /// 最多为初始的16倍，并且不超过剩余预算，直到用完max_evaluations或time_limit，返回最好的一次运行的结果。
+            /// 给出seed时结果可以逐位重复，与线程数无关。
+            /// history为True时在字典中附上每一代的历史最佳偏差、最佳位置、平均偏差和位置的标准差，
+            /// 重启时各次运行的记录依次连在一起。
+            /// callback是每一代之后调用的Python函数 callback(step, best_loss, best_position, statistics)，
+            /// statistics包含平均偏差、位置的标准差和计算偏差的次数，返回真值时提前停止。
+            /// uncertainty为True时在字典中附上最佳位置处由雅可比矩阵估计的标准误差、协方差矩阵、
+            /// 相关系数矩阵和残差方差
+            #[pyfunction(
+                boundary = "\"absorb\"",
+                inertia = "\"constant\"",
+                w_end = "0.4",
+                topology = "\"star\"",
+                radius = "1",
+                variant = "\"classic\"",
+                stall_steps = "None",
+                loss_tolerance = "0.0",
+                relative_tolerance = "0.0",
+                position_tolerance = "None",
+                max_evaluations = "None",
+                time_limit = "None",
+                full_output = "false",
+                restarts = "false",
+                population_growth = "2.0",
+                seed = "None",
+                history = "false",
+                uncertainty = "false",
+                callback = "None"
+            )]
+            #[allow(clippy::too_many_arguments)]
+            pub fn classic_pso(
+                py: Python,
+                position_max: Vec<f64>,
+                position_min: Vec<f64>,
+                x_data: Vec<f64>,
+                y_data: Vec<f64>,
+                num_of_particles: usize,
+                w: f64,
+                c1: f64,
+                c2: f64,
+                steps: usize,
+                boundary: &str,
+                inertia: &str,
+                w_end: f64,
+                topology: &str,
+                radius: usize,
+                variant: &str,
+                stall_steps: Option<usize>,
+                loss_tolerance: f64,
+                relative_tolerance: f64,
+                position_tolerance: Option<f64>,
+                max_evaluations: Option<usize>,
+                time_limit: Option<f64>,
+                full_output: bool,
+                restarts: bool,
+                population_growth: f64,
+                seed: Option<u64>,
+                history: bool,
+                uncertainty: bool,
+                callback: Option<PyObject>,
+            ) -> PyResult<PyObject> {
+                let options = PsoOptions {
+                    restart: restart(restarts, population_growth)?,
+                    history,
+                    uncertainty,
+                    ..pso_options(
+                        boundary,
+                        inertia,
+                        w_end,
+                        topology,
+                        radius,
+                        variant,
+                        termination(
+                            stall_steps,
+                            loss_tolerance,
+                            relative_tolerance,
+                            position_tolerance,
+                            max_evaluations,
+                            time_limit,
+                        )?,
+                        seed,
+                    )?
+                };
+                let mut callback = callback.map(PyCallback::new);
+                let mut observe = callback
+                    .as_mut()
+                    .map(|callback| move |progress: &Progress| callback.observe(progress));
+                let (result, report) = optimizer_core::classic_pso(
+                    &position_max,
+                    &position_min,
+                    &x_data,
+                    &y_data,
+                    num_of_particles,
+                    w,
+                    c1,
+                    c2,
+                    steps,
+                    $model,
+                    &options,
+                    observe.as_mut().map(|observe| observe as Callback),
+                )
+                .map_err(ValueError::py_err)?;
+                if let Some(callback) = callback.as_mut() {
+                    callback.take_error(py)?;
+                }
+                with_report(py, result, &report, full_output || history || uncertainty)
+            }
+            /// 导出的寻找多个极小值的粒子群优化函数，返回每个物种的最佳偏差和位置，按偏差从小到大排列。
+            /// niche_radius是物种的半径，以各维度上下限之间的距离为单位，其余设置与classic_pso相同
+            #[pyfunction(
+                niche_radius = "0.1",
+                boundary = "\"absorb\"",
+                inertia = "\"constant\"",
+                w_end = "0.4",
+                variant = "\"classic\"",
+                stall_steps = "None",
+                loss_tolerance = "0.0",
+                relative_tolerance = "0.0",
+                position_tolerance = "None",
+                max_evaluations = "None",
+                time_limit = "None",
+                full_output = "false",
+                seed = "None",
+                history = "false",
+                callback = "None"
+            )]
+            #[allow(clippy::too_many_arguments)]
+            pub fn niching_pso(
+                py: Python,
+                position_max: Vec<f64>,
+                position_min: Vec<f64>,
+                x_data: Vec<f64>,
+                y_data: Vec<f64>,
+                num_of_particles: usize,
+                w: f64,
+                c1: f64,
+                c2: f64,
+                steps: usize,
+                niche_radius: f64,
+                boundary: &str,
+                inertia: &str,
+                w_end: f64,
+                variant: &str,
+                stall_steps: Option<usize>,
+                loss_tolerance: f64,
+                relative_tolerance: f64,
+                position_tolerance: Option<f64>,
+                max_evaluations: Option<usize>,
+                time_limit: Option<f64>,
+                full_output: bool,
+                seed: Option<u64>,
+                history: bool,
+                callback: Option<PyObject>,
+            ) -> PyResult<PyObject> {
+                // 物种拓扑由niching_pso设置
+                let options = PsoOptions {
+                    history,
+                    ..pso_options(
+                        boundary,
+                        inertia,
+                        w_end,
+                        "star",
+                        1,
+                        variant,
+                        termination(
+                            stall_steps,
+                            loss_tolerance,
+                            relative_tolerance,
+                            position_tolerance,
+                            max_evaluations,
+                            time_limit,
+                        )?,
+                        seed,
+                    )?
+                };
+                let mut callback = callback.map(PyCallback::new);
+                let mut observe = callback
+                    .as_mut()
+                    .map(|callback| move |progress: &Progress| callback.observe(progress));
+                let (result, report) = optimizer_core::niching_pso(
+                    &position_max,
+                    &position_min,
+                    &x_data,
+                    &y_data,
+                    num_of_particles,
+                    w,
+                    c1,
+                    c2,
+                    steps,
+                    $model,
+                    niche_radius,
+                    &options,
+                    observe.as_mut().map(|observe| observe as Callback),
+                )
+                .map_err(ValueError::py_err)?;
+                if let Some(callback) = callback.as_mut() {
+                    callback.take_error(py)?;
+                }
+                with_report(py, result, &report, full_output || history)
+            }
+            /// 导出的以自助法估计参数置信区间的粒子群优化函数，返回最佳偏差、最佳位置、
+            /// 每个参数的百分位数置信区间的下限和上限，以及每次重新拟合得到的最佳位置。
+            /// 重抽样方式可选 "residual" 和 "pairs"，replicates是重新拟合的次数，confidence是置信水平，
+            /// 重新拟合时粒子从最佳位置附近warm_radius倍的上下限距离内开始。
+            /// 给出seed时结果可以逐位重复，其余设置与classic_pso相同，full_output的字典是第一次拟合的概况
+            #[pyfunction(
+                resampling = "\"residual\"",
+                replicates = "200",
+                confidence = "0.95",
+                warm_radius = "0.1",
+                boundary = "\"absorb\"",
+                inertia = "\"constant\"",
+                w_end = "0.4",
+                topology = "\"star\"",
+                radius = "1",
+                variant = "\"classic\"",
+                stall_steps = "None",
+                loss_tolerance = "0.0",
+                relative_tolerance = "0.0",
+                position_tolerance = "None",
+                max_evaluations = "None",
+                time_limit = "None",
+                full_output = "false",
+                seed = "None"
+            )]
+            #[allow(clippy::too_many_arguments)]
+            pub fn bootstrap_pso(
+                py: Python,
+                position_max: Vec<f64>,
+                position_min: Vec<f64>,
+                x_data: Vec<f64>,
+                y_data: Vec<f64>,
+                num_of_particles: usize,
+                w: f64,
+                c1: f64,
+                c2: f64,
+                steps: usize,
+                resampling: &str,
+                replicates: usize,
+                confidence: f64,
+                warm_radius: f64,
+                boundary: &str,
+                inertia: &str,
+                w_end: f64,
+                topology: &str,
+                radius: usize,
+                variant: &str,
+                stall_steps: Option<usize>,
+                loss_tolerance: f64,
+                relative_tolerance: f64,
+                position_tolerance: Option<f64>,
+                max_evaluations: Option<usize>,
+                time_limit: Option<f64>,
+                full_output: bool,
+                seed: Option<u64>,
+            ) -> PyResult<PyObject> {
+                let bootstrap = Bootstrap::new(
+                    Resampling::from_name(resampling).map_err(ValueError::py_err)?,
+                    replicates,
+                    confidence,
+                    warm_radius,
+                )
+                .map_err(ValueError::py_err)?;
+                let options = pso_options(
+                    boundary,
+                    inertia,
+                    w_end,
+                    topology,
+                    radius,
+                    variant,
+                    termination(
+                        stall_steps,
+                        loss_tolerance,
+                        relative_tolerance,
+                        position_tolerance,
+                        max_evaluations,
+                        time_limit,
+                    )?,
+                    seed,
+                )?;
+                let ((loss, best_position, _, _), intervals, report) = py
+                    .allow_threads(|| {
+                        optimizer_core::bootstrap_pso(
+                            &position_max,
+                            &position_min,
+                            &x_data,
+                            &y_data,
+                            num_of_particles,
+                            w,
+                            c1,
+                            c2,
+                            steps,
+                            $model,
+                            &options,
+                            &bootstrap,
+                        )
+                    })
+                    .map_err(ValueError::py_err)?;
+                with_report(
+                    py,
+                    (
+                        loss,
+                        best_position,
+                        intervals.lower,
+                        intervals.upper,
+                        intervals.samples,
+                    ),
+                    &report,
+                    full_output,
+                )
+            }
+            /// 导出的在拟合后以仿射不变系综采样后验分布的粒子群优化函数，返回最佳偏差、最佳位置、
+            /// 每个行走者每一步的位置、每个行走者的接受率和每个参数的自相关时间。
+            /// 最终的粒子群加上小的高斯扰动作为行走者走mcmc_steps步，粒子数至少是参数个数的两倍，
+            /// stretch是伸缩移动的系数，
+            /// 似然是噪声方差为noise_variance的高斯分布，默认取最佳拟合的残差方差，先验在位置上下限之内均匀。
+            /// 给出seed时结果可以逐位重复，其余设置与classic_pso相同，full_output的字典是拟合的概况
+            #[pyfunction(
+                mcmc_steps = "1000",
+                stretch = "2.0",
+                noise_variance = "None",
+                boundary = "\"absorb\"",
+                inertia = "\"constant\"",
+                w_end = "0.4",
+                topology = "\"star\"",
+                radius = "1",
+                variant = "\"classic\"",
+                stall_steps = "None",
+                loss_tolerance = "0.0",
+                relative_tolerance = "0.0",
+                position_tolerance = "None",
+                max_evaluations = "None",
+                time_limit = "None",
+                full_output = "false",
+                seed = "None"
+            )]
+            #[allow(clippy::too_many_arguments)]
+            pub fn mcmc_pso(
+                py: Python,
+                position_max: Vec<f64>,
+                position_min: Vec<f64>,
+                x_data: Vec<f64>,
+                y_data: Vec<f64>,
+                num_of_particles: usize,
+                w: f64,
+                c1: f64,
+                c2: f64,
+                steps: usize,
+                mcmc_steps: usize,
+                stretch: f64,
+                noise_variance: Option<f64>,
+                boundary: &str,
+                inertia: &str,
+                w_end: f64,
+                topology: &str,
+                radius: usize,
+                variant: &str,
+                stall_steps: Option<usize>,
+                loss_tolerance: f64,
+                relative_tolerance: f64,
+                position_tolerance: Option<f64>,
+                max_evaluations: Option<usize>,
+                time_limit: Option<f64>,
+                full_output: bool,
+                seed: Option<u64>,
+            ) -> PyResult<PyObject> {
+                let sampler = Sampler::new(mcmc_steps, stretch, noise_variance)
+                    .map_err(ValueError::py_err)?;
+                let options = pso_options(
+                    boundary,
+                    inertia,
+                    w_end,
+                    topology,
+                    radius,
+                    variant,
+                    termination(
+                        stall_steps,
+                        loss_tolerance,
+                        relative_tolerance,
+                        position_tolerance,
+                        max_evaluations,
+                        time_limit,
+                    )?,
+                    seed,
+                )?;
+                let ((loss, best_position, _, _), sampled, report) = py
+                    .allow_threads(|| {
+                        optimizer_core::mcmc_pso(
+                            &position_max,
+                            &position_min,
+                            &x_data,
+                            &y_data,
+                            num_of_particles,
+                            w,
+                            c1,
+                            c2,
+                            steps,
+                            $model,
+                            &options,
+                            &sampler,
+                        )
+                    })
+                    .map_err(ValueError::py_err)?;
+                with_report(
+                    py,
+                    (
+                        loss,
+                        best_position,
+                        sampled.chains,
+                        sampled.acceptance_fractions,
+                        sampled.autocorrelation_times,
+                    ),
+                    &report,
+                    full_output,
+                )
+            }
+            /// 导出的剖面似然的粒子群优化函数，返回最佳偏差、最佳位置、每个参数的格点、
+            /// 固定在各个格点上重新拟合其余参数得到的偏差，以及每个参数的似然比置信区间的下限和上限。
+            /// points是每个参数在上下限之间的格点数，confidence是置信水平，
+            /// 重新拟合时粒子从最佳位置附近warm_radius倍的上下限距离内开始，
+            /// noise_variance是数据的噪声方差，默认取最佳拟合的残差方差，偏差曲线没有越过阈值时区间取到上下限。
+            /// 给出seed时结果可以逐位重复，其余设置与classic_pso相同，full_output的字典是第一次拟合的概况
+            #[pyfunction(
+                points = "21",
+                confidence = "0.95",
+                warm_radius = "0.5",
+                noise_variance = "None",
+                boundary = "\"absorb\"",
+                inertia = "\"constant\"",
+                w_end = "0.4",
+                topology = "\"star\"",
+                radius = "1",
+                variant = "\"classic\"",
+                stall_steps = "None",
+                loss_tolerance = "0.0",
+                relative_tolerance = "0.0",
+                position_tolerance = "None",
+                max_evaluations = "None",
+                time_limit = "None",
+                full_output = "false",
+                seed = "None"
+            )]
+            #[allow(clippy::too_many_arguments)]
+            pub fn profile_pso(
+                py: Python,
+                position_max: Vec<f64>,
+                position_min: Vec<f64>,
+                x_data: Vec<f64>,
+                y_data: Vec<f64>,
+                num_of_particles: usize,
+                w: f64,
+                c1: f64,
+                c2: f64,
+                steps: usize,
+                points: usize,
+                confidence: f64,
+                warm_radius: f64,
+                noise_variance: Option<f64>,
+                boundary: &str,
+                inertia: &str,
+                w_end: f64,
+                topology: &str,
+                radius: usize,
+                variant: &str,
+                stall_steps: Option<usize>,
+                loss_tolerance: f64,
+                relative_tolerance: f64,
+                position_tolerance: Option<f64>,
+                max_evaluations: Option<usize>,
+                time_limit: Option<f64>,
+                full_output: bool,
+                seed: Option<u64>,
+            ) -> PyResult<PyObject> {
+                let profile = Profile::new(points, confidence, warm_radius, noise_variance)
+                    .map_err(ValueError::py_err)?;
+                let options = pso_options(
+                    boundary,
+                    inertia,
+                    w_end,
+                    topology,
+                    radius,
+                    variant,
+                    termination(
+                        stall_steps,
+                        loss_tolerance,
+                        relative_tolerance,
+                        position_tolerance,
+                        max_evaluations,
+                        time_limit,
+                    )?,
+                    seed,
+                )?;
+                let ((loss, best_position, _, _), profiles, report) = py
+                    .allow_threads(|| {
+                        optimizer_core::profile_pso(
+                            &position_max,
+                            &position_min,
+                            &x_data,
+                            &y_data,
+                            num_of_particles,
+                            w,
+                            c1,
+                            c2,
+                            steps,
+                            $model,
+                            &options,
+                            &profile,
+                        )
+                    })
+                    .map_err(ValueError::py_err)?;
+                with_report(
+                    py,
+                    (
+                        loss,
+                        best_position,
+                        profiles.grids,
+                        profiles.losses,
+                        profiles.lower,
+                        profiles.upper,
+                    ),
+                    &report,
+                    full_output,
+                )
+            }
+
+            /// 把生成的函数加入Python模块
+            pub fn register(m: &PyModule) -> PyResult<()> {
+                m.add_wrapped(wrap_pyfunction!(classic_pso))?;
+                m.add_wrapped(wrap_pyfunction!(niching_pso))?;
+                m.add_wrapped(wrap_pyfunction!(bootstrap_pso))?;
+                m.add_wrapped(wrap_pyfunction!(mcmc_pso))?;
+                m.add_wrapped(wrap_pyfunction!(profile_pso))?;
+                Ok(())
+            }
+        }
+    };
+}
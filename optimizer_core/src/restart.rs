@@ -0,0 +1,188 @@
+use crate::history::History;
+use crate::termination::{Report, StopReason, Termination};
+use std::time::{Duration, Instant};
+/// 重启后的种群大小最多是初始大小的这么多倍
+const MAX_POPULATION_FACTOR: f64 = 16.0;
+/// 种群停滞时重新初始化（IPOP），保留历史最佳结果，直到用完计算偏差的次数或运行时间
+#[derive(Debug, Clone, Copy)]
+pub struct Restart {
+    /// 每次重启时种群大小乘以的倍数，为1时种群大小不变
+    pub population_growth: f64,
+}
+impl Restart {
+    /// 种群大小的倍数必须是不小于1的有限值
+    pub fn new(population_growth: f64) -> Result<Self, String> {
+        if population_growth.is_finite() && population_growth >= 1.0 {
+            Ok(Restart { population_growth })
+        } else {
+            Err(format!(
+                "population growth must be a finite number not less than 1, but got {}",
+                population_growth
+            ))
+        }
+    }
+}
+/// 反复运行直到用完终止条件中的计算偏差次数或运行时间，两者是所有运行合计的上限，
+/// 其余终止条件和最大代数判断每一次运行是否停滞，回调函数要求停止时不再重启。
+/// run以种群大小、本次运行的终止条件和之前最好的结果运行一次，返回结果、最佳偏差和概况，
+/// 它应当把之前最好的结果放入新的种群。
+/// 种群大小不超过初始大小的16倍，也不超过剩余预算能够初始化并演化一代的大小，
+/// 剩余预算连初始大小的种群都不够时不再重启。
+/// 返回最佳偏差最小的一次运行的结果，以及所有运行合计的概况
+pub fn with_restarts<T, F>(
+    restart: &Restart,
+    termination: &Termination,
+    num_of_particles: usize,
+    mut run: F,
+) -> Result<(T, Report), String>
+where
+    F: FnMut(usize, &Termination, Option<&T>) -> Result<(T, f64, Report), String>,
+{
+    if termination.max_evaluations.is_none() && termination.time_limit.is_none() {
+        return Err("restarts need max_evaluations or time_limit as a budget".to_string());
+    }
+    let start = Instant::now();
+    let mut best: Option<(T, f64)> = None;
+    let mut report = Report {
+        stop_reason: StopReason::MaxEvaluations,
+        steps: 0,
+        evaluations: 0,
+        restarts: 0,
+        history: History::default(),
+        uncertainty: None,
+    };
+    let max_population = num_of_particles as f64 * MAX_POPULATION_FACTOR;
+    let mut population = num_of_particles as f64;
+    loop {
+        // 本次运行只能使用剩余的预算
+        let run_termination = Termination {
+            max_evaluations: termination
+                .max_evaluations
+                .map(|max| max.saturating_sub(report.evaluations)),
+            time_limit: termination
+                .time_limit
+                .map(|limit| limit.checked_sub(start.elapsed()).unwrap_or_default()),
+            ..termination.clone()
+        };
+        let incumbent = best.as_ref().map(|(result, _)| result);
+        let (result, loss, mut run_report) =
+            run(population.round() as usize, &run_termination, incumbent)?;
+        report.steps += run_report.steps;
+        report.evaluations += run_report.evaluations;
+        report.history.append(&mut run_report.history);
+        let improved = match &best {
+            Some((_, best_loss)) => loss < *best_loss,
+            None => true,
+        };
+        if improved {
+            best = Some((result, loss));
+        }
+        if run_report.stop_reason == StopReason::Callback {
+            report.stop_reason = StopReason::Callback;
+            break;
+        }
+        // 下一次运行要计算初始种群、放入的最好结果和至少一代的偏差
+        let affordable = |budget: f64| ((budget - 1.0) / 2.0).floor();
+        let mut next_population = (population * restart.population_growth).min(max_population);
+        if let Some(max_evaluations) = termination.max_evaluations {
+            let remaining = max_evaluations.saturating_sub(report.evaluations) as f64;
+            if affordable(remaining) < num_of_particles as f64 {
+                report.stop_reason = StopReason::MaxEvaluations;
+                break;
+            }
+            next_population = next_population.min(affordable(remaining));
+        }
+        if let Some(time_limit) = termination.time_limit {
+            let elapsed = start.elapsed();
+            let remaining = time_limit.checked_sub(elapsed).unwrap_or_default();
+            // 按之前每次计算偏差的平均用时估计剩余时间内能计算的次数
+            let per_evaluation = elapsed.as_secs_f64() / report.evaluations.max(1) as f64;
+            let budget = if per_evaluation > 0.0 {
+                remaining.as_secs_f64() / per_evaluation
+            } else {
+                f64::INFINITY
+            };
+            if remaining == Duration::default() || affordable(budget) < num_of_particles as f64 {
+                report.stop_reason = StopReason::TimeLimit;
+                break;
+            }
+            next_population = next_population.min(affordable(budget));
+        }
+        report.restarts += 1;
+        population = next_population;
+    }
+    Ok((best.unwrap().0, report))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 模拟一次运行：计算初始种群和放入的最好结果，然后每代计算一次种群，直到用完预算或演化5代。
+    /// 每次运行的偏差依次是列表中的值
+    fn simulate(
+        max_evaluations: usize,
+        losses: &[f64],
+    ) -> (Vec<(usize, usize, Option<f64>)>, Report) {
+        let termination = Termination {
+            max_evaluations: Some(max_evaluations),
+            ..Termination::default()
+        };
+        let mut runs = Vec::new();
+        let (_, report) = with_restarts(
+            &Restart::new(2.0).unwrap(),
+            &termination,
+            10,
+            |population, termination: &Termination, incumbent: Option<&f64>| {
+                let budget = termination.max_evaluations.unwrap();
+                runs.push((population, budget, incumbent.cloned()));
+                let mut evaluations = population + incumbent.map_or(0, |_| 1);
+                let mut steps = 0;
+                while steps < 5 && evaluations < budget {
+                    evaluations += population;
+                    steps += 1;
+                }
+                let loss = losses[(runs.len() - 1).min(losses.len() - 1)];
+                let run_report = Report {
+                    stop_reason: StopReason::LossTolerance,
+                    steps,
+                    evaluations,
+                    restarts: 0,
+                    history: History::default(),
+                    uncertainty: None,
+                };
+                Ok((loss, loss, run_report))
+            },
+        )
+        .unwrap();
+        (runs, report)
+    }
+
+    #[test]
+    fn population_is_capped() {
+        let (runs, report) = simulate(1_000_000, &[1.0]);
+        let populations = runs.iter().map(|r| r.0).collect::<Vec<usize>>();
+        assert_eq!(&populations[..6], &[10, 20, 40, 80, 160, 160]);
+        assert!(populations.iter().all(|p| *p <= 160));
+        assert_eq!(report.stop_reason, StopReason::MaxEvaluations);
+    }
+
+    #[test]
+    fn restarts_fit_the_budget() {
+        let (runs, report) = simulate(200, &[1.0]);
+        // 每次重启时剩余预算都够初始化种群、放入最好的结果并演化一代
+        assert!(runs.len() > 1);
+        for (population, budget, _) in &runs[1..] {
+            assert!(2 * population < *budget);
+            assert!(*population >= 10);
+        }
+        assert_eq!(runs.len(), report.restarts + 1);
+        assert!(report.evaluations <= 200);
+    }
+
+    #[test]
+    fn incumbent_is_passed_on() {
+        let (runs, _) = simulate(1000, &[3.0, 5.0, 2.0, 4.0]);
+        let incumbents = runs.iter().map(|r| r.2).collect::<Vec<Option<f64>>>();
+        assert_eq!(&incumbents[..4], &[None, Some(3.0), Some(3.0), Some(2.0)]);
+    }
+}
@@ -0,0 +1,13 @@
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+/// 由种子创建随机数发生器，种子为None时从操作系统取种子，结果不可重复
+pub fn seeded(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    }
+}
+/// 从发生器中派生出一个独立的随机数流。每个粒子各用一个流，结果就与线程数无关
+pub fn stream(rng: &mut StdRng) -> StdRng {
+    StdRng::seed_from_u64(rng.next_u64())
+}
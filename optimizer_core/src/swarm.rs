@@ -0,0 +1,394 @@
+mod apso;
+mod clpso;
+pub mod particle;
+use self::apso::{elitist_candidate, Apso, State};
+use self::clpso::Clpso;
+use self::particle::Particle;
+use crate::boundary::Boundary;
+use crate::model::{comparable_loss, Model};
+use crate::seed::{seeded, stream};
+use crate::topology::{species_seeds, Neighbourhood, Topology};
+use crate::variant::Variant;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rayon::prelude::*;
+/// 一个粒子群
+#[derive(Debug)]
+pub struct Swarm<M: Model> {
+    /// 粒子的总数
+    pub num_of_particles: usize,
+    /// 粒子的列表
+    pub particle_list: Vec<Particle>,
+    /// 全局历史最佳位置
+    pub global_best_position: Vec<f64>,
+    /// 全局历史最佳偏差
+    pub global_best_loss: f64,
+    /// 位置的上下限
+    position_max: Vec<f64>,
+    position_min: Vec<f64>,
+    /// 待拟合的数据
+    x_data: Vec<f64>,
+    y_data: Vec<f64>,
+    /// 待拟合的模型
+    model: M,
+    /// 粒子之间的拓扑结构
+    neighbourhood: Neighbourhood,
+    /// 粒子群优化的变体
+    variant: Variant,
+    /// CLPSO的状态，其他变体为None
+    clpso: Option<Clpso>,
+    /// APSO的状态，其他变体为None
+    apso: Option<Apso>,
+    /// 计算偏差的次数
+    evaluations: usize,
+    /// 粒子群整体使用的随机数发生器
+    rng: StdRng,
+    /// 每个粒子各自的随机数流
+    particle_rngs: Vec<StdRng>,
+}
+
+impl<M: Model> Swarm<M> {
+    /// 创建一个新粒子群，给出种子时结果可以重复
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        num_of_particles: usize,
+        position_max: &[f64],
+        position_min: &[f64],
+        x_data: &[f64],
+        y_data: &[f64],
+        model: M,
+        topology: Topology,
+        variant: Variant,
+        seed: Option<u64>,
+    ) -> Self {
+        // 按粒子的下标依次派生随机数流
+        let mut rng = seeded(seed);
+        let mut particle_rngs = (0..num_of_particles)
+            .map(|_| stream(&mut rng))
+            .collect::<Vec<StdRng>>();
+        // 构造一个向量把粒子创建好然后推进去
+        let mut particle_list: Vec<Particle> = Vec::with_capacity(num_of_particles);
+        for particle_rng in particle_rngs.iter_mut() {
+            particle_list.push(Particle::new(
+                particle_rng,
+                position_max,
+                position_min,
+                x_data,
+                y_data,
+                &model,
+            ));
+        }
+        // 比较出当前（所有粒子均未运动）的历史最佳位置和偏差
+        let (global_best_loss, global_best_position) = compare(&particle_list, None);
+        let position_range = position_max
+            .iter()
+            .zip(position_min)
+            .map(|(a, b)| a - b)
+            .collect();
+        let clpso = match variant {
+            Variant::Clpso => Some(Clpso::new(&mut rng, &particle_list)),
+            _ => None,
+        };
+        let apso = match variant {
+            Variant::Apso => Some(Apso::new()),
+            _ => None,
+        };
+        Swarm {
+            num_of_particles,
+            particle_list,
+            global_best_loss,
+            global_best_position,
+            position_max: position_max.to_vec(),
+            position_min: position_min.to_vec(),
+            x_data: x_data.to_vec(),
+            y_data: y_data.to_vec(),
+            model,
+            neighbourhood: Neighbourhood::new(&mut rng, topology, num_of_particles, position_range),
+            variant,
+            clpso,
+            apso,
+            evaluations: num_of_particles,
+            rng,
+            particle_rngs,
+        }
+    }
+    /// 粒子群的演化，QPSO时w是收缩扩张系数，APSO时w是精英学习的扰动幅度
+    pub fn evolution(&mut self, w: f64, c1: f64, c2: f64, boundary: Boundary) {
+        // APSO由粒子的分布估计演化状态，自动调整惯性权重和加速系数，传入的w是精英学习的扰动幅度
+        let sigma = w;
+        let (w, c1, c2, state) = match self.apso.as_mut() {
+            Some(apso) => {
+                let (w, c1, c2, state) = apso.adapt(&mut self.rng, &self.particle_list, c1, c2);
+                (w, c1, c2, Some(state))
+            }
+            None => (w, c1, c2, None),
+        };
+        let gbp = &self.global_best_position;
+        let x_data = &self.x_data;
+        let y_data = &self.y_data;
+        let model = &self.model;
+        let particle_rngs = &mut self.particle_rngs;
+        match self.variant {
+            Variant::Clpso => {
+                // CLPSO中每一个粒子向各自的榜样学习
+                let clpso = self.clpso.as_mut().unwrap();
+                let exemplar_positions = clpso.exemplar_positions(&self.particle_list);
+                let previous_best_loss = self
+                    .particle_list
+                    .iter()
+                    .map(|p| p.local_best_loss)
+                    .collect::<Vec<f64>>();
+                self.particle_list
+                    .par_iter_mut()
+                    .zip(particle_rngs.par_iter_mut())
+                    .zip(&exemplar_positions)
+                    .for_each(|((p, rng), e)| {
+                        p.learn(rng, w, c1, boundary, e, x_data, y_data, model)
+                    });
+                clpso.refresh(&mut self.rng, &previous_best_loss, &self.particle_list);
+            }
+            Variant::Classic | Variant::Qpso | Variant::Apso => {
+                // 每一个粒子各自运动，追随全局或者邻居中的历史最佳位置
+                let best_positions = self.neighbourhood.best_positions(&self.particle_list);
+                let social_best_position = |i: usize| match &best_positions {
+                    None => gbp.as_slice(),
+                    Some(best_positions) => best_positions[i].as_slice(),
+                };
+                if self.variant == Variant::Qpso {
+                    // 所有粒子的历史最佳位置的平均
+                    let mut mean_best_position = vec![0.0; gbp.len()];
+                    for p in &self.particle_list {
+                        for (m, lbp) in mean_best_position.iter_mut().zip(&p.local_best_position) {
+                            *m += lbp / self.num_of_particles as f64;
+                        }
+                    }
+                    self.particle_list
+                        .par_iter_mut()
+                        .zip(particle_rngs.par_iter_mut())
+                        .enumerate()
+                        .for_each(|(i, (p, rng))| {
+                            p.quantum(
+                                rng,
+                                w,
+                                &mean_best_position,
+                                social_best_position(i),
+                                boundary,
+                                x_data,
+                                y_data,
+                                model,
+                            )
+                        });
+                } else {
+                    self.particle_list
+                        .par_iter_mut()
+                        .zip(particle_rngs.par_iter_mut())
+                        .enumerate()
+                        .for_each(|(i, (p, rng))| {
+                            p.evolution(
+                                rng,
+                                w,
+                                c1,
+                                c2,
+                                boundary,
+                                social_best_position(i),
+                                x_data,
+                                y_data,
+                                model,
+                            )
+                        });
+                }
+            }
+        }
+        self.evaluations += self.num_of_particles;
+        // 运动完之后更新历史最佳位置和偏差
+        let (global_best_loss, global_best_position) =
+            compare(&self.particle_list, Some(self.global_best_loss));
+        if global_best_loss >= self.global_best_loss {
+            self.neighbourhood.stagnate(&mut self.rng);
+        }
+        self.global_best_position = global_best_position;
+        self.global_best_loss = global_best_loss;
+        if state == Some(State::Convergence) {
+            self.elitist_learning(sigma);
+        }
+    }
+    /// 把粒子重新放到center附近，每个维度在center两侧radius倍的上下限距离内均匀分布，
+    /// 截断到上下限内，第一个粒子正好放在center上。用于从已知的最佳位置附近开始优化
+    pub fn warm_start(&mut self, center: &[f64], radius: f64) {
+        let position_max = &self.position_max;
+        let position_min = &self.position_min;
+        let x_data = &self.x_data;
+        let y_data = &self.y_data;
+        let model = &self.model;
+        self.particle_list
+            .par_iter_mut()
+            .zip(self.particle_rngs.par_iter_mut())
+            .enumerate()
+            .for_each(|(i, (p, rng))| {
+                let position = if i == 0 {
+                    center.to_vec()
+                } else {
+                    center
+                        .iter()
+                        .zip(position_max)
+                        .zip(position_min)
+                        .map(|((c, max), min)| {
+                            let offset = (max - min) * radius * (2.0 * rng.gen::<f64>() - 1.0);
+                            (c + offset).max(*min).min(*max)
+                        })
+                        .collect()
+                };
+                p.relocate(position, x_data, y_data, model);
+            });
+        self.evaluations += self.num_of_particles;
+        let (global_best_loss, global_best_position) = compare(&self.particle_list, None);
+        self.global_best_loss = global_best_loss;
+        self.global_best_position = global_best_position;
+    }
+    /// 把历史最佳偏差最大的粒子移到给定的位置，例如重启前最好的结果，使新的粒子群不会丢掉它
+    pub fn inject(&mut self, position: &[f64]) {
+        let worst = (0..self.num_of_particles)
+            .max_by(|a, b| {
+                self.particle_list[*a]
+                    .local_best_loss
+                    .partial_cmp(&self.particle_list[*b].local_best_loss)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .unwrap();
+        let particle = &mut self.particle_list[worst];
+        particle.relocate(position.to_vec(), &self.x_data, &self.y_data, &self.model);
+        self.evaluations += 1;
+        if particle.local_best_loss < self.global_best_loss {
+            self.global_best_loss = particle.local_best_loss;
+            self.global_best_position = particle.local_best_position.clone();
+        }
+    }
+    /// APSO在收敛状态时扰动全局历史最佳位置
+    fn elitist_learning(&mut self, sigma: f64) {
+        let candidate = elitist_candidate(
+            &mut self.rng,
+            &self.global_best_position,
+            &self.position_max,
+            &self.position_min,
+            sigma,
+        );
+        let loss = comparable_loss(self.model.calc_loss(&candidate, &self.x_data, &self.y_data));
+        self.evaluations += 1;
+        // 更好时作为历史最佳偏差最小的粒子的历史最佳位置，否则取代历史最佳偏差最大的粒子
+        let mut best = 0;
+        let mut worst = 0;
+        for (i, p) in self.particle_list.iter().enumerate() {
+            if p.local_best_loss < self.particle_list[best].local_best_loss {
+                best = i;
+            }
+            if p.local_best_loss > self.particle_list[worst].local_best_loss {
+                worst = i;
+            }
+        }
+        if loss < self.global_best_loss {
+            let best_particle = &mut self.particle_list[best];
+            best_particle.local_best_position = candidate.clone();
+            best_particle.local_best_loss = loss;
+            self.global_best_loss = loss;
+            self.global_best_position = candidate;
+        } else if worst != best {
+            let worst_particle = &mut self.particle_list[worst];
+            worst_particle.position = candidate.clone();
+            worst_particle.local_best_position = candidate;
+            worst_particle.loss = loss;
+            worst_particle.local_best_loss = loss;
+        }
+    }
+    /// 把粒子按历史最佳位置聚成半径为radius的物种，返回每个物种的最佳偏差和位置，按偏差从小到大排列。
+    /// 只有一个粒子的物种没有收敛到极小值，不计入结果，除非所有物种都只有一个粒子
+    pub fn species(&self, radius: f64) -> Vec<(f64, Vec<f64>)> {
+        let position_range = self
+            .position_max
+            .iter()
+            .zip(&self.position_min)
+            .map(|(a, b)| a - b)
+            .collect::<Vec<f64>>();
+        let (seeds, species) = species_seeds(&self.particle_list, &position_range, radius);
+        let mut sizes = vec![0; self.num_of_particles];
+        species.iter().for_each(|seed| sizes[*seed] += 1);
+        let result = |min_size: usize| {
+            seeds
+                .iter()
+                .filter(|i| sizes[**i] >= min_size)
+                .map(|i| {
+                    let p = &self.particle_list[*i];
+                    (p.local_best_loss, p.local_best_position.clone())
+                })
+                .collect::<Vec<(f64, Vec<f64>)>>()
+        };
+        match result(2) {
+            r if r.is_empty() => result(1),
+            r => r,
+        }
+    }
+    /// 计算偏差的次数
+    pub fn evaluations(&self) -> usize {
+        self.evaluations
+    }
+    /// 粒子群整体使用的随机数发生器，用于需要与粒子群一起重复的随机数
+    pub(crate) fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+    /// 所有粒子当前位置的平均偏差
+    pub fn mean_loss(&self) -> f64 {
+        self.particle_list.iter().map(|p| p.loss).sum::<f64>() / self.num_of_particles as f64
+    }
+    /// 粒子群所拟合的模型
+    pub fn model(&self) -> &M {
+        &self.model
+    }
+    /// 测量粒子群的位置的平均值和标准差
+    pub fn result_evaluate(&self) -> (Vec<f64>, Vec<f64>) {
+        // 测量平均值
+        let position_len = self.global_best_position.len();
+        let mut sum_of_position = vec![0.0; position_len];
+        self.particle_list
+            .iter()
+            .for_each(|ptcl| ptcl.add_position_to_vec(&mut sum_of_position));
+        let mean_position = sum_of_position
+            .iter()
+            .map(|sop| *sop / self.num_of_particles as f64)
+            .collect::<Vec<f64>>();
+        // 测量标准差
+        let mut sum_of_square_error = vec![0.0; position_len];
+        self.particle_list.iter().for_each(|ptcl| {
+            ptcl.add_position_square_error_to_vec(&mut sum_of_square_error, &mean_position)
+        });
+        let standard_var_of_position = sum_of_square_error
+            .iter()
+            .map(|sose| (*sose / self.num_of_particles as f64).sqrt())
+            .collect::<Vec<f64>>();
+        (mean_position, standard_var_of_position)
+    }
+}
+/// 比较粒子群和历史最佳偏差，返回新的历史最佳偏差和历史最佳位置
+pub fn compare(
+    particle_list: &[Particle],
+    current_global_best_loss: Option<f64>,
+) -> (f64, Vec<f64>) {
+    let mut candidate_global_best_loss = current_global_best_loss;
+    let mut candidate_global_best_position = None;
+    particle_list
+        .iter()
+        .for_each(|a| match candidate_global_best_loss {
+            None => {
+                candidate_global_best_loss = Some(a.local_best_loss);
+                candidate_global_best_position = Some(a.local_best_position.clone());
+            }
+            Some(x) => {
+                if a.local_best_loss <= x {
+                    candidate_global_best_loss = Some(a.local_best_loss);
+                    candidate_global_best_position = Some(a.local_best_position.clone());
+                }
+            }
+        });
+    (
+        candidate_global_best_loss.unwrap(),
+        candidate_global_best_position.unwrap(),
+    )
+}
@@ -0,0 +1,260 @@
+use super::particle::Particle;
+use rand::seq::index;
+use rand::Rng;
+use rayon::prelude::*;
+/// 加速系数的调整幅度在这个范围内随机选取
+const ACCELERATION_RATE: (f64, f64) = (0.05, 0.1);
+/// 加速系数的取值范围
+const ACCELERATION_RANGE: (f64, f64) = (1.5, 2.5);
+/// 两个加速系数之和的上限
+const ACCELERATION_SUM_MAX: f64 = 4.0;
+/// 粒子数超过这个值时，平均距离只对随机抽取的这么多个粒子计算
+const DISTANCE_SAMPLE: usize = 64;
+/// 粒子群的演化状态
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum State {
+    Exploration,
+    Exploitation,
+    Convergence,
+    JumpingOut,
+}
+impl State {
+    /// 演化状态通常的转移顺序中的下一个状态
+    fn next(self) -> Self {
+        match self {
+            State::Exploration => State::Exploitation,
+            State::Exploitation => State::Convergence,
+            State::Convergence => State::JumpingOut,
+            State::JumpingOut => State::Exploration,
+        }
+    }
+    /// 进化因子f属于这个状态的模糊隶属度
+    fn membership(self, f: f64) -> f64 {
+        match self {
+            State::Exploration => {
+                if f <= 0.4 || f > 0.8 {
+                    0.0
+                } else if f <= 0.6 {
+                    5.0 * f - 2.0
+                } else if f <= 0.7 {
+                    1.0
+                } else {
+                    -10.0 * f + 8.0
+                }
+            }
+            State::Exploitation => {
+                if f <= 0.2 || f > 0.6 {
+                    0.0
+                } else if f <= 0.3 {
+                    10.0 * f - 2.0
+                } else if f <= 0.4 {
+                    1.0
+                } else {
+                    -5.0 * f + 3.0
+                }
+            }
+            State::Convergence => {
+                if f <= 0.1 {
+                    1.0
+                } else if f <= 0.3 {
+                    -5.0 * f + 1.5
+                } else {
+                    0.0
+                }
+            }
+            State::JumpingOut => {
+                if f <= 0.7 {
+                    0.0
+                } else if f <= 0.9 {
+                    5.0 * f - 3.5
+                } else {
+                    1.0
+                }
+            }
+        }
+    }
+}
+/// APSO的状态，记录上一代的演化状态和当前的加速系数
+#[derive(Debug)]
+pub(crate) struct Apso {
+    state: State,
+    /// 加速系数，第一次演化时取传入的值
+    acceleration: Option<(f64, f64)>,
+}
+impl Apso {
+    pub(crate) fn new() -> Self {
+        Apso {
+            state: State::Exploration,
+            acceleration: None,
+        }
+    }
+    /// 由粒子的分布估计演化状态，返回这一代的惯性权重、加速系数和演化状态
+    pub(crate) fn adapt<R: Rng>(
+        &mut self,
+        rng: &mut R,
+        particle_list: &[Particle],
+        c1: f64,
+        c2: f64,
+    ) -> (f64, f64, f64, State) {
+        let f = evolutionary_factor(rng, particle_list);
+        self.state = classify(f, self.state);
+        // w = 1 / (1 + 1.5 exp(-2.6 f))，在 [0.4, 0.9] 之间
+        let w = 1.0 / (1.0 + 1.5 * (-2.6 * f).exp());
+        let delta = rng.gen_range(ACCELERATION_RATE.0, ACCELERATION_RATE.1);
+        let (mut c1, mut c2) = self.acceleration.unwrap_or((c1, c2));
+        let (d1, d2) = match self.state {
+            State::Exploration => (delta, -delta),
+            State::Exploitation => (0.5 * delta, -0.5 * delta),
+            State::Convergence => (0.5 * delta, 0.5 * delta),
+            State::JumpingOut => (-delta, delta),
+        };
+        c1 = (c1 + d1)
+            .max(ACCELERATION_RANGE.0)
+            .min(ACCELERATION_RANGE.1);
+        c2 = (c2 + d2)
+            .max(ACCELERATION_RANGE.0)
+            .min(ACCELERATION_RANGE.1);
+        if c1 + c2 > ACCELERATION_SUM_MAX {
+            let scale = ACCELERATION_SUM_MAX / (c1 + c2);
+            c1 *= scale;
+            c2 *= scale;
+        }
+        self.acceleration = Some((c1, c2));
+        (w, c1, c2, self.state)
+    }
+}
+/// 进化因子 f = (d_g - d_min) / (d_max - d_min)，d是每个粒子到其他粒子的平均距离，
+/// d_g是历史最佳偏差最小的粒子的平均距离。
+/// 精确计算要 O(n^2 D) 次运算，粒子数超过64时每个粒子只和同一组随机抽取的64个粒子比较，
+/// 运算量降为 O(64 n D)
+fn evolutionary_factor<R: Rng>(rng: &mut R, particle_list: &[Particle]) -> f64 {
+    let n = particle_list.len();
+    if n < 2 {
+        return 0.0;
+    }
+    let others = if n > DISTANCE_SAMPLE + 1 {
+        index::sample(rng, n, DISTANCE_SAMPLE).into_vec()
+    } else {
+        (0..n).collect()
+    };
+    let mean_distance = particle_list
+        .par_iter()
+        .enumerate()
+        .map(|(i, a)| {
+            let (sum, count) = others
+                .iter()
+                .filter(|j| **j != i)
+                .map(|j| {
+                    a.position
+                        .iter()
+                        .zip(&particle_list[*j].position)
+                        .map(|(x, y)| (x - y).powi(2))
+                        .sum::<f64>()
+                        .sqrt()
+                })
+                .fold((0.0, 0), |(sum, count), d| (sum + d, count + 1));
+            sum / count as f64
+        })
+        .collect::<Vec<f64>>();
+    let mut best = 0;
+    for (i, p) in particle_list.iter().enumerate() {
+        if p.local_best_loss < particle_list[best].local_best_loss {
+            best = i;
+        }
+    }
+    let d_max = mean_distance
+        .iter()
+        .cloned()
+        .fold(f64::NEG_INFINITY, f64::max);
+    let d_min = mean_distance.iter().cloned().fold(f64::INFINITY, f64::min);
+    if d_max > d_min {
+        (mean_distance[best] - d_min) / (d_max - d_min)
+    } else {
+        0.0
+    }
+}
+/// 由进化因子的模糊隶属度确定演化状态。属于多个状态时，优先保持上一代的状态，
+/// 其次按通常的转移顺序进入下一个状态
+fn classify(f: f64, previous: State) -> State {
+    let states = [
+        State::Exploration,
+        State::Exploitation,
+        State::Convergence,
+        State::JumpingOut,
+    ];
+    if previous.membership(f) > 0.0 {
+        return previous;
+    }
+    if previous.next().membership(f) > 0.0 {
+        return previous.next();
+    }
+    let mut state = previous;
+    let mut max_membership = 0.0;
+    for s in states.iter() {
+        if s.membership(f) > max_membership {
+            max_membership = s.membership(f);
+            state = *s;
+        }
+    }
+    state
+}
+/// 精英学习：在全局历史最佳位置的一个随机维度上加上正态分布的扰动，
+/// 扰动的标准差是这个维度的范围乘以sigma，结果截断到上下限内
+pub(crate) fn elitist_candidate<R: Rng>(
+    rng: &mut R,
+    global_best_position: &[f64],
+    position_max: &[f64],
+    position_min: &[f64],
+    sigma: f64,
+) -> Vec<f64> {
+    let mut candidate = global_best_position.to_vec();
+    let d = rng.gen_range(0, candidate.len());
+    // Box-Muller变换得到标准正态分布的随机数
+    let u1 = 1.0 - rng.gen::<f64>();
+    let u2 = rng.gen::<f64>();
+    let gaussian = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+    candidate[d] += (position_max[d] - position_min[d]) * sigma * gaussian;
+    candidate[d] = candidate[d].max(position_min[d]).min(position_max[d]);
+    candidate
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::Expression;
+    use crate::seed::seeded;
+
+    /// 一维的粒子放在给出的位置上，偏差是 a^2，离0越近越好
+    fn particles(positions: &[f64]) -> Vec<Particle> {
+        let model = Expression::with_inferred_params("a*x").unwrap();
+        let mut rng = seeded(Some(1));
+        positions
+            .iter()
+            .map(|p| {
+                let mut particle =
+                    Particle::new(&mut rng, &[10.0], &[-10.0], &[1.0], &[0.0], &model);
+                particle.relocate(vec![*p], &[1.0], &[0.0], &model);
+                particle
+            })
+            .collect()
+    }
+
+    #[test]
+    fn evolutionary_factor_range() {
+        let mut rng = seeded(Some(2));
+        for n in [10, 200].iter() {
+            // 最好的粒子远离其余聚在一起的粒子，进化因子接近1
+            let mut positions = (0..*n)
+                .map(|i| 5.0 + i as f64 / *n as f64)
+                .collect::<Vec<f64>>();
+            positions[0] = 0.0;
+            let f = evolutionary_factor(&mut rng, &particles(&positions));
+            assert!((0.9..=1.0).contains(&f), "n = {}, f = {}", n, f);
+            // 最好的粒子在均匀分布的中心，进化因子接近0
+            let positions = (0..*n)
+                .map(|i| i as f64 - (*n / 2) as f64)
+                .collect::<Vec<f64>>();
+            let f = evolutionary_factor(&mut rng, &particles(&positions));
+            assert!((0.0..0.1).contains(&f), "n = {}, f = {}", n, f);
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use super::particle::Particle;
+use rand::Rng;
+/// 粒子的历史最佳连续这么多代没有改进时重新选取榜样
+const REFRESHING_GAP: usize = 7;
+/// CLPSO的状态，包括每个粒子在每个维度上学习的榜样
+#[derive(Debug)]
+pub(crate) struct Clpso {
+    /// 每个粒子向其他粒子学习的概率
+    learning_probability: Vec<f64>,
+    /// 每个粒子在每个维度上学习的粒子下标
+    exemplars: Vec<Vec<usize>>,
+    /// 每个粒子的历史最佳连续没有改进的代数
+    stagnation: Vec<usize>,
+}
+impl Clpso {
+    pub(crate) fn new<R: Rng>(rng: &mut R, particle_list: &[Particle]) -> Self {
+        let n = particle_list.len();
+        // Pc_i = 0.05 + 0.45 * (exp(10 i / (n - 1)) - 1) / (exp(10) - 1)
+        let learning_probability = (0..n)
+            .map(|i| {
+                let t = if n > 1 {
+                    i as f64 / (n - 1) as f64
+                } else {
+                    0.0
+                };
+                0.05 + 0.45 * ((10.0 * t).exp() - 1.0) / (10.0f64.exp() - 1.0)
+            })
+            .collect::<Vec<f64>>();
+        let mut clpso = Clpso {
+            learning_probability,
+            exemplars: vec![Vec::new(); n],
+            stagnation: vec![0; n],
+        };
+        for i in 0..n {
+            clpso.assign_exemplars(rng, i, particle_list);
+        }
+        clpso
+    }
+    /// 每个粒子按各自的榜样在每个维度上组合出的学习目标
+    pub(crate) fn exemplar_positions(&self, particle_list: &[Particle]) -> Vec<Vec<f64>> {
+        self.exemplars
+            .iter()
+            .map(|exemplar| {
+                exemplar
+                    .iter()
+                    .enumerate()
+                    .map(|(d, j)| particle_list[*j].local_best_position[d])
+                    .collect()
+            })
+            .collect()
+    }
+    /// 运动之后，对历史最佳长时间没有改进的粒子重新选取榜样
+    pub(crate) fn refresh<R: Rng>(
+        &mut self,
+        rng: &mut R,
+        previous_best_loss: &[f64],
+        particle_list: &[Particle],
+    ) {
+        for (i, particle) in particle_list.iter().enumerate() {
+            if particle.local_best_loss < previous_best_loss[i] {
+                self.stagnation[i] = 0;
+            } else {
+                self.stagnation[i] += 1;
+            }
+            if self.stagnation[i] >= REFRESHING_GAP {
+                self.stagnation[i] = 0;
+                self.assign_exemplars(rng, i, particle_list);
+            }
+        }
+    }
+    /// 为粒子i在每个维度上选取榜样，以学习概率用锦标赛从另外两个粒子中选出较好的一个，
+    /// 否则向自己学习。所有维度都向自己学习时随机挑一个维度向其他粒子学习
+    fn assign_exemplars<R: Rng>(&mut self, rng: &mut R, i: usize, particle_list: &[Particle]) {
+        let n = particle_list.len();
+        let dims = particle_list[i].position.len();
+        let mut exemplar = vec![i; dims];
+        if n > 1 {
+            let tournament = |rng: &mut R| {
+                let mut pick = || loop {
+                    let j = rng.gen_range(0, n);
+                    if j != i {
+                        break j;
+                    }
+                };
+                let (a, b) = (pick(), pick());
+                if particle_list[a].local_best_loss <= particle_list[b].local_best_loss {
+                    a
+                } else {
+                    b
+                }
+            };
+            for e in exemplar.iter_mut() {
+                if rng.gen::<f64>() < self.learning_probability[i] {
+                    *e = tournament(rng);
+                }
+            }
+            if exemplar.iter().all(|e| *e == i) {
+                let d = rng.gen_range(0, dims);
+                exemplar[d] = tournament(rng);
+            }
+        }
+        self.exemplars[i] = exemplar;
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::Expression;
+    use crate::seed::seeded;
+
+    /// 二维的粒子放在给出的位置上，历史最佳偏差是 (a + b)^2
+    fn particles(positions: &[[f64; 2]]) -> Vec<Particle> {
+        let model = Expression::with_inferred_params("a*x+b").unwrap();
+        let mut rng = seeded(Some(1));
+        positions
+            .iter()
+            .map(|p| {
+                let mut particle = Particle::new(
+                    &mut rng,
+                    &[10.0, 10.0],
+                    &[-10.0, -10.0],
+                    &[1.0],
+                    &[0.0],
+                    &model,
+                );
+                particle.relocate(p.to_vec(), &[1.0], &[0.0], &model);
+                particle
+            })
+            .collect()
+    }
+
+    #[test]
+    fn learning_probability() {
+        let mut rng = seeded(Some(1));
+        let list = particles(&[[0.0, 0.0]; 10]);
+        let clpso = Clpso::new(&mut rng, &list);
+        let pc = &clpso.learning_probability;
+        assert!((pc[0] - 0.05).abs() < 1e-12);
+        assert!((pc[9] - 0.5).abs() < 1e-12);
+        assert!(pc.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn exemplars_learn_from_others() {
+        let mut rng = seeded(Some(1));
+        let list = particles(&[[1.0, 1.0], [2.0, 2.0], [3.0, 3.0], [4.0, 4.0], [5.0, 5.0]]);
+        let mut wins = [0; 5];
+        for _ in 0..50 {
+            let clpso = Clpso::new(&mut rng, &list);
+            for (i, exemplar) in clpso.exemplars.iter().enumerate() {
+                assert_eq!(exemplar.len(), 2);
+                // 至少在一个维度上向其他粒子学习
+                assert!(exemplar.iter().any(|e| *e != i));
+                for e in exemplar.iter().filter(|e| **e != i) {
+                    wins[*e] += 1;
+                }
+            }
+            // 学习目标在每个维度上取榜样的历史最佳位置
+            let targets = clpso.exemplar_positions(&list);
+            for (exemplar, target) in clpso.exemplars.iter().zip(&targets) {
+                for d in 0..2 {
+                    assert_eq!(target[d], list[exemplar[d]].local_best_position[d]);
+                }
+            }
+        }
+        // 锦标赛中较好的粒子更常胜出，最差的粒子只有两次都被抽中时才胜出
+        assert!(wins.windows(2).all(|w| w[0] > w[1]), "{:?}", wins);
+    }
+
+    #[test]
+    fn two_particles_learn_from_each_other() {
+        let mut rng = seeded(Some(1));
+        let list = particles(&[[1.0, 1.0], [2.0, 2.0]]);
+        let clpso = Clpso::new(&mut rng, &list);
+        for (i, exemplar) in clpso.exemplars.iter().enumerate() {
+            assert!(exemplar.iter().all(|e| *e == i || *e == 1 - i));
+        }
+    }
+
+    #[test]
+    fn refresh_after_stagnation() {
+        let mut rng = seeded(Some(1));
+        let list = particles(&[[1.0, 1.0], [2.0, 2.0], [3.0, 3.0], [4.0, 4.0]]);
+        let mut clpso = Clpso::new(&mut rng, &list);
+        let unchanged = list.iter().map(|p| p.local_best_loss).collect::<Vec<f64>>();
+        for _ in 0..REFRESHING_GAP - 1 {
+            clpso.refresh(&mut rng, &unchanged, &list);
+        }
+        assert!(clpso.stagnation.iter().all(|s| *s == REFRESHING_GAP - 1));
+        // 第一个粒子改进了，计数清零，其余的到达间隔后重新选取榜样，计数也清零
+        let mut previous = unchanged.clone();
+        previous[0] = f64::INFINITY;
+        clpso.refresh(&mut rng, &previous, &list);
+        assert!(clpso.stagnation.iter().all(|s| *s == 0));
+    }
+}
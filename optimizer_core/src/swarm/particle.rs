@@ -0,0 +1,324 @@
+use crate::boundary::Boundary;
+use crate::model::{comparable_loss, Model};
+use rand::Rng;
+/// 这是粒子群算法中的粒子
+#[derive(Debug)]
+pub struct Particle {
+    /// 粒子的位置
+    pub position: Vec<f64>,
+    /// 位置的上边界
+    position_max: Vec<f64>,
+    /// 位置的下边界
+    position_min: Vec<f64>,
+    /// 粒子的速度
+    pub velocity: Vec<f64>,
+    /// 速度的上限
+    velocity_max: Vec<f64>,
+    /// 速度的反方向上限
+    velocity_min: Vec<f64>,
+    /// 当前位置的偏差
+    pub loss: f64,
+    /// 历史中的最好位置
+    pub local_best_position: Vec<f64>,
+    /// 历史中的最好偏差
+    pub local_best_loss: f64,
+}
+impl Particle {
+    /// 构建一个新粒子
+    pub fn new<R: Rng, M: Model>(
+        rng: &mut R,
+        position_max: &[f64],
+        position_min: &[f64],
+        x_data: &[f64],
+        y_data: &[f64],
+        model: &M,
+    ) -> Self {
+        // 将位置上下限拷贝
+        let p_max = position_max.to_owned();
+        let p_min = position_min.to_owned();
+        // 计算出位置上下限之间的距离
+        let position_range: Vec<f64> = p_max.iter().zip(&p_min).map(|(a, b)| a - b).collect();
+        // 以均匀分布选取粒子的初始位置
+        let position: Vec<f64> = position_range
+            .iter()
+            .map(|a| a * rng.gen::<f64>())
+            .zip(&p_min)
+            .map(|(a, b)| a + b)
+            .collect();
+        // 粒子的初始速度设为位置极差的-0.1倍
+        let velocity: Vec<f64> = position_range.iter().map(|p| -0.1 * p).collect();
+        // 速度上限（正反方向）设为位置极差的1/5
+        let velocity_max = position_range.iter().map(|a| a / 5.0).collect();
+        let velocity_min = position_range.iter().map(|a| -a / 5.0).collect();
+        // 初始的历史最佳位置就是当前位置
+        let local_best_position = position.clone();
+        // 由当前位置和待拟合数据算出偏差，也是初始的历史最佳偏差
+        let loss = comparable_loss(model.calc_loss(&position, x_data, y_data));
+        Particle {
+            position,
+            loss,
+            position_max: p_max,
+            position_min: p_min,
+            velocity,
+            velocity_max,
+            velocity_min,
+            local_best_position,
+            local_best_loss: loss,
+        }
+    }
+    /// 粒子的运动
+    #[allow(clippy::too_many_arguments)]
+    pub fn evolution<R: Rng, M: Model>(
+        &mut self,
+        rng: &mut R,
+        w: f64,
+        c1: f64,
+        c2: f64,
+        boundary: Boundary,
+        social_best_position: &[f64],
+        x_data: &[f64],
+        y_data: &[f64],
+        model: &M,
+    ) {
+        // 更新速度 v = w * v + c1 * rand * (lbp - p) + c2 * rand * (sbp - p)
+        self.velocity = self
+            .position
+            .iter()
+            .zip(&self.local_best_position)
+            .zip(social_best_position)
+            .zip(&self.velocity)
+            .map(|(((p, lbp), sbp), v)| {
+                w * v + c1 * rng.gen::<f64>() * (lbp - p) + c2 * rng.gen::<f64>() * (sbp - p)
+            })
+            .collect();
+        self.moving(rng, boundary, x_data, y_data, model);
+    }
+    /// CLPSO中粒子的运动，每个维度只向各自的榜样学习
+    #[allow(clippy::too_many_arguments)]
+    pub fn learn<R: Rng, M: Model>(
+        &mut self,
+        rng: &mut R,
+        w: f64,
+        c: f64,
+        boundary: Boundary,
+        exemplar_position: &[f64],
+        x_data: &[f64],
+        y_data: &[f64],
+        model: &M,
+    ) {
+        // 更新速度 v = w * v + c * rand * (exemplar - p)
+        self.velocity = self
+            .position
+            .iter()
+            .zip(exemplar_position)
+            .zip(&self.velocity)
+            .map(|((p, e), v)| w * v + c * rng.gen::<f64>() * (e - p))
+            .collect();
+        self.moving(rng, boundary, x_data, y_data, model);
+    }
+    /// 控制速度的上限，按速度更新位置
+    fn moving<R: Rng, M: Model>(
+        &mut self,
+        rng: &mut R,
+        boundary: Boundary,
+        x_data: &[f64],
+        y_data: &[f64],
+        model: &M,
+    ) {
+        // 控制速度的上限
+        for ((v, vmax), vmin) in self
+            .velocity
+            .iter_mut()
+            .zip(&self.velocity_max)
+            .zip(&self.velocity_min)
+        {
+            *v = v.max(*vmin).min(*vmax);
+        }
+        // 更新位置 p = p + v
+        for (p, v) in self.position.iter_mut().zip(&self.velocity) {
+            *p += *v;
+        }
+        self.settle(rng, boundary, x_data, y_data, model);
+    }
+    /// QPSO中粒子的运动，位置在吸引子附近按 p ± beta * |mbest - x| * ln(1/u) 采样，
+    /// 吸引子是个体和社会历史最佳位置的随机加权平均
+    #[allow(clippy::too_many_arguments)]
+    pub fn quantum<R: Rng, M: Model>(
+        &mut self,
+        rng: &mut R,
+        beta: f64,
+        mean_best_position: &[f64],
+        social_best_position: &[f64],
+        boundary: Boundary,
+        x_data: &[f64],
+        y_data: &[f64],
+        model: &M,
+    ) {
+        for ((((x, v), lbp), sbp), mbp) in self
+            .position
+            .iter_mut()
+            .zip(self.velocity.iter_mut())
+            .zip(&self.local_best_position)
+            .zip(social_best_position)
+            .zip(mean_best_position)
+        {
+            let phi = rng.gen::<f64>();
+            let attractor = phi * lbp + (1.0 - phi) * sbp;
+            let u = 1.0 - rng.gen::<f64>();
+            let step = beta * (mbp - *x).abs() * (1.0 / u).ln();
+            let new_x = if rng.gen::<bool>() {
+                attractor + step
+            } else {
+                attractor - step
+            };
+            // QPSO没有速度，这里记下位移
+            *v = new_x - *x;
+            *x = new_x;
+        }
+        self.settle(rng, boundary, x_data, y_data, model);
+    }
+    /// 越过上下限时按边界处理方式移回范围内，再计算新位置的偏差
+    fn settle<R: Rng, M: Model>(
+        &mut self,
+        rng: &mut R,
+        boundary: Boundary,
+        x_data: &[f64],
+        y_data: &[f64],
+        model: &M,
+    ) {
+        for (((p, v), pmax), pmin) in self
+            .position
+            .iter_mut()
+            .zip(self.velocity.iter_mut())
+            .zip(&self.position_max)
+            .zip(&self.position_min)
+        {
+            boundary.apply(rng, p, v, *pmax, *pmin);
+        }
+        // 计算新位置的偏差
+        let loss = comparable_loss(model.calc_loss(&self.position, x_data, y_data));
+        self.loss = loss;
+        // 如果新的偏差好于历史最佳，则将历史最佳偏差和位置更新
+        if loss < self.local_best_loss {
+            self.local_best_loss = loss;
+            self.local_best_position = self.position.clone();
+        }
+    }
+    /// 把粒子移到新的位置，历史最佳位置也从这里重新开始
+    pub(crate) fn relocate<M: Model>(
+        &mut self,
+        position: Vec<f64>,
+        x_data: &[f64],
+        y_data: &[f64],
+        model: &M,
+    ) {
+        self.loss = comparable_loss(model.calc_loss(&position, x_data, y_data));
+        self.local_best_loss = self.loss;
+        self.local_best_position = position.clone();
+        self.position = position;
+    }
+    /// 将粒子的位置加到一个数组上去
+    pub(crate) fn add_position_to_vec(&self, sum_of_position: &mut [f64]) {
+        sum_of_position
+            .iter_mut()
+            .zip(&self.position)
+            .for_each(|(sop, p)| *sop += p)
+    }
+    /// 将粒子的位置距离平均位置的平方偏差加到一个数组上去
+    pub(crate) fn add_position_square_error_to_vec(
+        &self,
+        sum_of_square_error: &mut [f64],
+        mean_position: &[f64],
+    ) {
+        sum_of_square_error
+            .iter_mut()
+            .zip(&self.position)
+            .zip(mean_position)
+            .for_each(|((sose, p), mp)| *sose += (mp - p).powi(2))
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::Expression;
+    use crate::seed::seeded;
+
+    #[test]
+    fn quantum_samples_around_attractor() {
+        let model = Expression::with_inferred_params("a*x+b").unwrap();
+        let mut rng = seeded(Some(1));
+        let mut particle = Particle::new(
+            &mut rng,
+            &[10.0, 10.0],
+            &[-10.0, -10.0],
+            &[1.0],
+            &[0.0],
+            &model,
+        );
+        particle.relocate(vec![1.0, -2.0], &[1.0], &[0.0], &model);
+        let social_best = [3.0, -1.0];
+        for _ in 0..100 {
+            // 平均最佳位置就在粒子上时步长为0，新位置是两个最佳位置之间的吸引子
+            let (position, local_best) = (
+                particle.position.clone(),
+                particle.local_best_position.clone(),
+            );
+            particle.quantum(
+                &mut rng,
+                0.75,
+                &position,
+                &social_best,
+                Boundary::Absorb,
+                &[1.0],
+                &[0.0],
+                &model,
+            );
+            for d in 0..2 {
+                let (low, high) = if local_best[d] < social_best[d] {
+                    (local_best[d], social_best[d])
+                } else {
+                    (social_best[d], local_best[d])
+                };
+                assert!(particle.position[d] >= low && particle.position[d] <= high);
+                assert_eq!(particle.velocity[d], particle.position[d] - position[d]);
+            }
+            let loss = model.calc_loss(&particle.position, &[1.0], &[0.0]);
+            assert_eq!(particle.loss, loss);
+            assert!(particle.local_best_loss <= loss);
+        }
+    }
+
+    #[test]
+    fn quantum_step_scales_with_distance() {
+        let model = Expression::with_inferred_params("a*x+b").unwrap();
+        let mut rng = seeded(Some(2));
+        let mut particle = Particle::new(
+            &mut rng,
+            &[100.0, 100.0],
+            &[-100.0, -100.0],
+            &[1.0],
+            &[0.0],
+            &model,
+        );
+        // 吸引子固定在原点，位移的平均值是 beta * |mbest - x|
+        let (beta, distance) = (0.5, 4.0);
+        let mut total = 0.0;
+        let trials = 2000;
+        for _ in 0..trials {
+            particle.relocate(vec![0.0, 0.0], &[1.0], &[0.0], &model);
+            particle.quantum(
+                &mut rng,
+                beta,
+                &[distance, distance],
+                &[0.0, 0.0],
+                Boundary::Absorb,
+                &[1.0],
+                &[0.0],
+                &model,
+            );
+            total += particle.position[0].abs();
+        }
+        let mean = total / trials as f64;
+        assert!((mean - beta * distance).abs() < 0.2, "mean step {}", mean);
+    }
+}
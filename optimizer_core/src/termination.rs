@@ -0,0 +1,210 @@
+use crate::history::History;
+use crate::uncertainty::Uncertainty;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+/// 提前终止优化的条件，默认都不启用，只在达到最大代数时停止
+#[derive(Debug, Clone, Default)]
+pub struct Termination {
+    /// 最佳偏差在stall_steps代内的下降量不超过 loss_tolerance + relative_tolerance * |最佳偏差| 时停止，
+    /// 为None时不检查偏差
+    pub stall_steps: Option<usize>,
+    /// 偏差下降量的绝对容差
+    pub loss_tolerance: f64,
+    /// 偏差下降量的相对容差
+    pub relative_tolerance: f64,
+    /// 粒子位置在每个维度上的标准差都不超过这个值时停止，以各维度上下限之间的距离为单位
+    pub position_tolerance: Option<f64>,
+    /// 计算偏差的次数达到这个值时停止
+    pub max_evaluations: Option<usize>,
+    /// 运行时间达到这个值时停止
+    pub time_limit: Option<Duration>,
+}
+impl Termination {
+    /// 检查终止条件，stall_steps为0时第一代之后就会停止，所以至少是1
+    pub fn new(
+        stall_steps: Option<usize>,
+        loss_tolerance: f64,
+        relative_tolerance: f64,
+        position_tolerance: Option<f64>,
+        max_evaluations: Option<usize>,
+        time_limit: Option<Duration>,
+    ) -> Result<Self, String> {
+        if stall_steps == Some(0) {
+            return Err("stall_steps must be at least 1".to_string());
+        }
+        Ok(Termination {
+            stall_steps,
+            loss_tolerance,
+            relative_tolerance,
+            position_tolerance,
+            max_evaluations,
+            time_limit,
+        })
+    }
+}
+/// 优化停止的原因
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopReason {
+    /// 达到最大代数
+    MaxSteps,
+    /// 最佳偏差不再下降
+    LossTolerance,
+    /// 粒子已经聚集在一起
+    PositionTolerance,
+    /// 计算偏差的次数用完
+    MaxEvaluations,
+    /// 运行时间用完
+    TimeLimit,
+    /// 回调函数要求停止
+    Callback,
+}
+impl StopReason {
+    /// 停止原因的名字
+    pub fn name(self) -> &'static str {
+        match self {
+            StopReason::MaxSteps => "max_steps",
+            StopReason::LossTolerance => "loss_tolerance",
+            StopReason::PositionTolerance => "position_tolerance",
+            StopReason::MaxEvaluations => "max_evaluations",
+            StopReason::TimeLimit => "time_limit",
+            StopReason::Callback => "callback",
+        }
+    }
+}
+/// 优化过程的概况
+#[derive(Debug, Clone)]
+pub struct Report {
+    /// 停止的原因
+    pub stop_reason: StopReason,
+    /// 实际演化的代数
+    pub steps: usize,
+    /// 计算偏差的次数
+    pub evaluations: usize,
+    /// 重新初始化种群的次数
+    pub restarts: usize,
+    /// 每一代的记录，不要求记录时为空
+    pub history: History,
+    /// 最佳位置处参数的不确定度，不要求估计时为None
+    pub uncertainty: Option<Uncertainty>,
+}
+/// 每一代演化之后交给回调函数的信息
+#[derive(Debug)]
+pub struct Progress<'a> {
+    /// 已经演化的代数
+    pub step: usize,
+    /// 历史最佳偏差
+    pub best_loss: f64,
+    /// 历史最佳位置
+    pub best_position: &'a [f64],
+    /// 所有粒子当前位置的平均偏差
+    pub mean_loss: f64,
+    /// 粒子位置在每个维度上的标准差
+    pub spread: &'a [f64],
+    /// 计算偏差的次数
+    pub evaluations: usize,
+}
+/// 每一代演化之后调用的回调函数，返回true时提前停止
+pub type Callback<'a> = &'a mut dyn FnMut(&Progress) -> bool;
+/// 由秒数得到运行时间的上限，秒数必须是非负的有限值
+pub fn time_limit_from_secs(secs: f64) -> Result<Duration, String> {
+    if secs.is_finite() && secs >= 0.0 {
+        Ok(Duration::from_secs_f64(secs))
+    } else {
+        Err(format!(
+            "time limit must be a non-negative number of seconds, but got {}",
+            secs
+        ))
+    }
+}
+/// 在每一代之后检查终止条件
+#[derive(Debug)]
+pub struct Monitor {
+    termination: Termination,
+    /// 各维度上下限之间的距离
+    position_range: Vec<f64>,
+    start: Instant,
+    /// 最近若干代的最佳偏差
+    loss_history: VecDeque<f64>,
+}
+impl Monitor {
+    /// 开始计时
+    pub fn new(termination: &Termination, position_max: &[f64], position_min: &[f64]) -> Self {
+        Monitor {
+            termination: termination.clone(),
+            position_range: position_max
+                .iter()
+                .zip(position_min)
+                .map(|(a, b)| a - b)
+                .collect(),
+            start: Instant::now(),
+            loss_history: VecDeque::new(),
+        }
+    }
+    /// 是否需要每一代测量粒子位置的标准差
+    pub fn needs_spread(&self) -> bool {
+        self.termination.position_tolerance.is_some()
+    }
+    /// 检查一代演化之后是否应当停止，spread是粒子位置在每个维度上的标准差，不需要时可以为空
+    pub fn check(
+        &mut self,
+        best_loss: f64,
+        spread: &[f64],
+        evaluations: usize,
+    ) -> Option<StopReason> {
+        let termination = &self.termination;
+        if let Some(stall_steps) = termination.stall_steps {
+            self.loss_history.push_back(best_loss);
+            if self.loss_history.len() > stall_steps {
+                let old_loss = self.loss_history.pop_front().unwrap();
+                let tolerance =
+                    termination.loss_tolerance + termination.relative_tolerance * best_loss.abs();
+                if old_loss - best_loss <= tolerance {
+                    return Some(StopReason::LossTolerance);
+                }
+            }
+        }
+        if let Some(position_tolerance) = termination.position_tolerance {
+            let converged = spread
+                .iter()
+                .zip(&self.position_range)
+                .filter(|(_, r)| **r > 0.0)
+                .all(|(s, r)| s / r <= position_tolerance);
+            if converged {
+                return Some(StopReason::PositionTolerance);
+            }
+        }
+        if let Some(max_evaluations) = termination.max_evaluations {
+            if evaluations >= max_evaluations {
+                return Some(StopReason::MaxEvaluations);
+            }
+        }
+        if let Some(time_limit) = termination.time_limit {
+            if self.start.elapsed() >= time_limit {
+                return Some(StopReason::TimeLimit);
+            }
+        }
+        None
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_termination() {
+        assert!(Termination::new(Some(0), 1e-3, 0.0, None, None, None).is_err());
+        assert!(Termination::new(Some(1), 0.0, 0.0, Some(0.0), None, None).is_ok());
+    }
+
+    #[test]
+    fn stall_steps() {
+        let termination = Termination::new(Some(2), 0.5, 0.0, None, None, None).unwrap();
+        let mut monitor = Monitor::new(&termination, &[1.0], &[0.0]);
+        // 偏差在两代内下降超过0.5时继续，不超过时停止
+        assert_eq!(monitor.check(10.0, &[], 0), None);
+        assert_eq!(monitor.check(9.0, &[], 0), None);
+        assert_eq!(monitor.check(8.0, &[], 0), None);
+        assert_eq!(monitor.check(7.9, &[], 0), None);
+        assert_eq!(monitor.check(7.8, &[], 0), Some(StopReason::LossTolerance));
+    }
+}
@@ -0,0 +1,264 @@
+use crate::swarm::particle::Particle;
+use rand::Rng;
+/// 随机拓扑中每个粒子告知的其他粒子个数
+const RANDOM_INFORMANTS: usize = 3;
+/// 粒子之间交换历史最佳位置的拓扑结构
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Topology {
+    /// 所有粒子都追随全局历史最佳位置
+    Star,
+    /// 粒子排成环，追随前后各radius个粒子中的最佳位置
+    Ring(usize),
+    /// 粒子排成首尾相接的网格，追随上下左右四个粒子中的最佳位置
+    VonNeumann,
+    /// 每个粒子随机告知3个粒子，全局最佳没有改进时重新连接
+    Random,
+    /// 粒子按历史最佳位置聚成半径为r的物种，追随所在物种中的最佳位置，
+    /// r以各维度上下限之间的距离为单位，用于寻找多个极小值
+    Species(f64),
+}
+#[allow(clippy::derivable_impls)]
+impl Default for Topology {
+    fn default() -> Self {
+        Topology::Star
+    }
+}
+impl Topology {
+    /// 由名字选取拓扑结构，可选 "star"、"ring"、"von-neumann" 和 "random"，radius只用于环形拓扑
+    pub fn from_name(name: &str, radius: usize) -> Result<Self, String> {
+        match name {
+            "star" => Ok(Topology::Star),
+            "ring" if radius == 0 => Err("ring topology needs a positive radius".to_string()),
+            "ring" => Ok(Topology::Ring(radius)),
+            "von-neumann" => Ok(Topology::VonNeumann),
+            "random" => Ok(Topology::Random),
+            _ => Err(format!("unknown topology `{}`", name)),
+        }
+    }
+}
+/// 每个粒子的邻居，星形拓扑不需要列出邻居
+#[derive(Debug)]
+pub(crate) struct Neighbourhood {
+    topology: Topology,
+    neighbours: Vec<Vec<usize>>,
+    /// 各维度上下限之间的距离，物种拓扑中用于计算距离
+    position_range: Vec<f64>,
+}
+impl Neighbourhood {
+    pub(crate) fn new<R: Rng>(
+        rng: &mut R,
+        topology: Topology,
+        num_of_particles: usize,
+        position_range: Vec<f64>,
+    ) -> Self {
+        let n = num_of_particles;
+        // 按偏移量取首尾相接的邻居，邻居包括粒子自己
+        let by_offsets = |offsets: &[usize]| {
+            (0..n)
+                .map(|i| {
+                    let mut neighbours = vec![i];
+                    for &k in offsets {
+                        neighbours.push((i + k) % n);
+                        neighbours.push((i + n - k % n) % n);
+                    }
+                    neighbours
+                })
+                .collect()
+        };
+        let neighbours = match topology {
+            Topology::Star | Topology::Species(_) => Vec::new(),
+            Topology::Ring(radius) => by_offsets(&(1..=radius).collect::<Vec<usize>>()),
+            Topology::VonNeumann => {
+                let columns = (n as f64).sqrt().ceil() as usize;
+                by_offsets(&[1, columns])
+            }
+            Topology::Random => random_neighbours(rng, n),
+        };
+        Neighbourhood {
+            topology,
+            neighbours,
+            position_range,
+        }
+    }
+    /// 全局最佳没有改进时调用，随机拓扑会重新连接
+    pub(crate) fn stagnate<R: Rng>(&mut self, rng: &mut R) {
+        if self.topology == Topology::Random {
+            self.neighbours = random_neighbours(rng, self.neighbours.len());
+        }
+    }
+    /// 每个粒子的邻居中的历史最佳位置，星形拓扑返回None，此时使用全局历史最佳位置
+    pub(crate) fn best_positions(&self, particle_list: &[Particle]) -> Option<Vec<Vec<f64>>> {
+        match self.topology {
+            Topology::Star => return None,
+            Topology::Species(radius) => {
+                let (_, species) = species_seeds(particle_list, &self.position_range, radius);
+                return Some(
+                    species
+                        .iter()
+                        .map(|seed| particle_list[*seed].local_best_position.clone())
+                        .collect(),
+                );
+            }
+            _ => (),
+        }
+        Some(
+            self.neighbours
+                .iter()
+                .map(|neighbours| {
+                    let best = neighbours
+                        .iter()
+                        .min_by(|a, b| {
+                            particle_list[**a]
+                                .local_best_loss
+                                .partial_cmp(&particle_list[**b].local_best_loss)
+                                .unwrap_or(std::cmp::Ordering::Equal)
+                        })
+                        .unwrap();
+                    particle_list[*best].local_best_position.clone()
+                })
+                .collect(),
+        )
+    }
+}
+/// 按历史最佳偏差从小到大依次选取物种的种子，与已有种子的距离都超过radius的粒子成为新的种子。
+/// 返回按偏差从小到大排列的种子，以及每个粒子所属物种的种子
+pub(crate) fn species_seeds(
+    particle_list: &[Particle],
+    position_range: &[f64],
+    radius: f64,
+) -> (Vec<usize>, Vec<usize>) {
+    let mut ranking = (0..particle_list.len()).collect::<Vec<usize>>();
+    ranking.sort_by(|a, b| {
+        particle_list[*a]
+            .local_best_loss
+            .partial_cmp(&particle_list[*b].local_best_loss)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    // 以各维度的范围为单位的距离，范围为0的维度不计入
+    let distance = |a: &[f64], b: &[f64]| {
+        a.iter()
+            .zip(b)
+            .zip(position_range)
+            .filter(|(_, r)| **r > 0.0)
+            .map(|((x, y), r)| ((x - y) / r).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    };
+    let mut seeds: Vec<usize> = Vec::new();
+    let mut species = vec![0; particle_list.len()];
+    for i in ranking {
+        let position = &particle_list[i].local_best_position;
+        match seeds
+            .iter()
+            .find(|s| distance(position, &particle_list[**s].local_best_position) <= radius)
+        {
+            Some(seed) => species[i] = *seed,
+            None => {
+                seeds.push(i);
+                species[i] = i;
+            }
+        }
+    }
+    (seeds, species)
+}
+/// 每个粒子告知自己和随机的若干个粒子，返回每个粒子能得知的粒子
+fn random_neighbours<R: Rng>(rng: &mut R, num_of_particles: usize) -> Vec<Vec<usize>> {
+    let mut neighbours: Vec<Vec<usize>> = (0..num_of_particles).map(|i| vec![i]).collect();
+    for j in 0..num_of_particles {
+        for _ in 0..RANDOM_INFORMANTS {
+            neighbours[rng.gen_range(0, num_of_particles)].push(j);
+        }
+    }
+    neighbours
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::Expression;
+    use crate::seed::seeded;
+
+    /// 一维的粒子放在给出的位置上，历史最佳偏差是 a^2
+    fn particles(positions: &[f64]) -> Vec<Particle> {
+        let model = Expression::with_inferred_params("a*x").unwrap();
+        let mut rng = seeded(Some(1));
+        positions
+            .iter()
+            .map(|p| {
+                let mut particle =
+                    Particle::new(&mut rng, &[10.0], &[-10.0], &[1.0], &[0.0], &model);
+                particle.relocate(vec![*p], &[1.0], &[0.0], &model);
+                particle
+            })
+            .collect()
+    }
+
+    fn sorted(mut v: Vec<usize>) -> Vec<usize> {
+        v.sort_unstable();
+        v.dedup();
+        v
+    }
+
+    #[test]
+    fn ring_neighbours() {
+        let mut rng = seeded(Some(1));
+        let ring = Neighbourhood::new(&mut rng, Topology::Ring(1), 5, vec![1.0]);
+        assert_eq!(sorted(ring.neighbours[0].clone()), vec![0, 1, 4]);
+        assert_eq!(sorted(ring.neighbours[2].clone()), vec![1, 2, 3]);
+        let ring = Neighbourhood::new(&mut rng, Topology::Ring(2), 7, vec![1.0]);
+        assert_eq!(sorted(ring.neighbours[6].clone()), vec![0, 1, 4, 5, 6]);
+    }
+
+    #[test]
+    fn von_neumann_neighbours() {
+        let mut rng = seeded(Some(1));
+        // 9个粒子排成3列，上下左右首尾相接
+        let grid = Neighbourhood::new(&mut rng, Topology::VonNeumann, 9, vec![1.0]);
+        assert_eq!(sorted(grid.neighbours[0].clone()), vec![0, 1, 3, 6, 8]);
+        assert_eq!(sorted(grid.neighbours[4].clone()), vec![1, 3, 4, 5, 7]);
+    }
+
+    #[test]
+    fn random_neighbours_rewire() {
+        let mut rng = seeded(Some(1));
+        let mut random = Neighbourhood::new(&mut rng, Topology::Random, 10, vec![1.0]);
+        let check = |neighbours: &[Vec<usize>]| {
+            // 每个粒子知道自己，并且恰好告知3个粒子
+            for (i, n) in neighbours.iter().enumerate() {
+                assert_eq!(n[0], i);
+            }
+            for j in 0..10 {
+                let informed = neighbours
+                    .iter()
+                    .map(|n| n[1..].iter().filter(|k| **k == j).count())
+                    .sum::<usize>();
+                assert_eq!(informed, RANDOM_INFORMANTS);
+            }
+        };
+        check(&random.neighbours);
+        let before = random.neighbours.clone();
+        random.stagnate(&mut rng);
+        check(&random.neighbours);
+        assert_ne!(before, random.neighbours);
+    }
+
+    #[test]
+    fn neighbourhood_best() {
+        let mut rng = seeded(Some(1));
+        let list = particles(&[5.0, 4.0, 3.0, 1.0, 2.0]);
+        let star = Neighbourhood::new(&mut rng, Topology::Star, 5, vec![20.0]);
+        assert!(star.best_positions(&list).is_none());
+        let ring = Neighbourhood::new(&mut rng, Topology::Ring(1), 5, vec![20.0]);
+        let best = ring.best_positions(&list).unwrap();
+        let best = best.iter().map(|p| p[0]).collect::<Vec<f64>>();
+        assert_eq!(best, vec![2.0, 3.0, 1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn species_by_radius() {
+        // 范围为20，半径0.1即距离2以内属于同一物种
+        let list = particles(&[0.5, 1.5, 9.0, 8.0, -6.0]);
+        let (seeds, species) = species_seeds(&list, &[20.0], 0.1);
+        assert_eq!(seeds, vec![0, 4, 3]);
+        assert_eq!(species, vec![0, 0, 3, 3, 4]);
+    }
+}
@@ -0,0 +1,155 @@
+use crate::model::Model;
+/// 由雅可比矩阵估计的参数不确定度
+#[derive(Debug, Clone)]
+pub struct Uncertainty {
+    /// 每个参数的标准误差
+    pub standard_errors: Vec<f64>,
+    /// 参数的协方差矩阵 s^2 (J^T J)^-1
+    pub covariance: Vec<Vec<f64>>,
+    /// 参数的相关系数矩阵
+    pub correlation: Vec<Vec<f64>>,
+    /// 残差方差 s^2 = 平方偏差 / (数据点数 - 参数个数)
+    pub residual_variance: f64,
+}
+/// 在最佳位置处由雅可比矩阵估计参数的不确定度。模型给出解析梯度时使用解析梯度，否则用中心差分。
+/// 数据点不多于参数个数或者 J^T J 奇异时，参数无法确定，协方差和标准误差都是无穷大
+pub fn uncertainty<M: Model>(
+    model: &M,
+    params: &[f64],
+    x_data: &[f64],
+    y_data: &[f64],
+) -> Uncertainty {
+    let num_of_params = params.len();
+    let degrees_of_freedom = x_data.len().saturating_sub(num_of_params);
+    let residual_variance = if degrees_of_freedom > 0 {
+        model.calc_loss(params, x_data, y_data) / degrees_of_freedom as f64
+    } else {
+        f64::INFINITY
+    };
+    let jacobian = x_data
+        .iter()
+        .map(|x| {
+            model
+                .gradient(*x, params)
+                .unwrap_or_else(|| numerical_gradient(model, *x, params))
+        })
+        .collect::<Vec<Vec<f64>>>();
+    // J^T J
+    let mut normal = vec![vec![0.0; num_of_params]; num_of_params];
+    for row in &jacobian {
+        for i in 0..num_of_params {
+            for j in 0..num_of_params {
+                normal[i][j] += row[i] * row[j];
+            }
+        }
+    }
+    let covariance = match invert(&normal) {
+        Some(inverse) if residual_variance.is_finite() => inverse
+            .iter()
+            .map(|row| row.iter().map(|c| c * residual_variance).collect())
+            .collect::<Vec<Vec<f64>>>(),
+        _ => vec![vec![f64::INFINITY; num_of_params]; num_of_params],
+    };
+    let standard_errors = (0..num_of_params)
+        .map(|i| covariance[i][i].sqrt())
+        .collect::<Vec<f64>>();
+    let correlation = (0..num_of_params)
+        .map(|i| {
+            (0..num_of_params)
+                .map(|j| covariance[i][j] / (standard_errors[i] * standard_errors[j]))
+                .collect()
+        })
+        .collect();
+    Uncertainty {
+        standard_errors,
+        covariance,
+        correlation,
+        residual_variance,
+    }
+}
+/// 用中心差分求模型在x处对各个参数的梯度，步长随参数的大小缩放
+fn numerical_gradient<M: Model>(model: &M, x: f64, params: &[f64]) -> Vec<f64> {
+    let mut shifted = params.to_vec();
+    (0..params.len())
+        .map(|i| {
+            let h = f64::EPSILON.cbrt() * params[i].abs().max(1.0);
+            shifted[i] = params[i] + h;
+            let forward = model.eval(x, &shifted);
+            shifted[i] = params[i] - h;
+            let backward = model.eval(x, &shifted);
+            shifted[i] = params[i];
+            (forward - backward) / (2.0 * h)
+        })
+        .collect()
+}
+/// 用部分主元的高斯-约当消元求逆矩阵，奇异时返回None
+pub(crate) fn invert(matrix: &[Vec<f64>]) -> Option<Vec<Vec<f64>>> {
+    let n = matrix.len();
+    let mut a = matrix.to_vec();
+    let mut inverse = (0..n)
+        .map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+        .collect::<Vec<Vec<f64>>>();
+    // 主元相对于矩阵最大元素过小时视为奇异
+    let scale = matrix
+        .iter()
+        .flatten()
+        .fold(0.0f64, |max, x| max.max(x.abs()));
+    for col in 0..n {
+        let pivot = (col..n).max_by(|i, j| {
+            a[*i][col]
+                .abs()
+                .partial_cmp(&a[*j][col].abs())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })?;
+        if a[pivot][col].abs() <= scale * 1e-14 || a[pivot][col].is_nan() {
+            return None;
+        }
+        a.swap(col, pivot);
+        inverse.swap(col, pivot);
+        let p = a[col][col];
+        for j in 0..n {
+            a[col][j] /= p;
+            inverse[col][j] /= p;
+        }
+        for row in 0..n {
+            if row != col {
+                let factor = a[row][col];
+                for j in 0..n {
+                    a[row][j] -= factor * a[col][j];
+                    inverse[row][j] -= factor * inverse[col][j];
+                }
+            }
+        }
+    }
+    Some(inverse)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invert_known_matrix() {
+        let matrix = vec![
+            vec![4.0, 7.0, 2.0],
+            vec![3.0, 6.0, 1.0],
+            vec![2.0, 5.0, 3.0],
+        ];
+        let inverse = invert(&matrix).unwrap();
+        for (i, row) in matrix.iter().enumerate() {
+            for j in 0..3 {
+                let product: f64 = row.iter().zip(&inverse).map(|(a, b)| a * b[j]).sum();
+                let identity = if i == j { 1.0 } else { 0.0 };
+                assert!((product - identity).abs() < 1e-12);
+            }
+        }
+        // 需要换主元的矩阵
+        let swapped = invert(&[vec![0.0, 1.0], vec![2.0, 0.0]]).unwrap();
+        assert_eq!(swapped, vec![vec![0.0, 0.5], vec![1.0, 0.0]]);
+    }
+
+    #[test]
+    fn invert_singular_matrix() {
+        assert!(invert(&[vec![1.0, 2.0], vec![2.0, 4.0]]).is_none());
+        assert!(invert(&[vec![0.0, 0.0], vec![0.0, 0.0]]).is_none());
+    }
+}
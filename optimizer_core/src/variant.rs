@@ -0,0 +1,41 @@
+/// QPSO的收缩扩张系数的初值
+pub(crate) const QPSO_BETA_START: f64 = 1.0;
+/// QPSO的收缩扩张系数的终值
+pub(crate) const QPSO_BETA_END: f64 = 0.5;
+/// APSO精英学习的扰动幅度的初值
+pub(crate) const APSO_SIGMA_START: f64 = 1.0;
+/// APSO精英学习的扰动幅度的终值
+pub(crate) const APSO_SIGMA_END: f64 = 0.1;
+/// 粒子群优化的变体
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Variant {
+    /// 经典的速度更新，追随个体和社会的历史最佳位置
+    Classic,
+    /// CLPSO：每个粒子的每个维度用锦标赛选出一个粒子，向它的历史最佳位置学习，
+    /// 只使用加速系数c1
+    Clpso,
+    /// QPSO：没有速度，粒子在吸引子附近按量子势阱采样新位置，
+    /// 不使用w、c1和c2，收缩扩张系数从1.0线性递减到0.5
+    Qpso,
+    /// APSO：由粒子的分布估计演化状态，自动调整w、c1和c2，收敛时对全局历史最佳位置做精英学习，
+    /// c1和c2只作为初值
+    Apso,
+}
+#[allow(clippy::derivable_impls)]
+impl Default for Variant {
+    fn default() -> Self {
+        Variant::Classic
+    }
+}
+impl Variant {
+    /// 由名字选取变体，可选 "classic"、"clpso"、"qpso" 和 "apso"
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "classic" => Ok(Variant::Classic),
+            "clpso" => Ok(Variant::Clpso),
+            "qpso" => Ok(Variant::Qpso),
+            "apso" => Ok(Variant::Apso),
+            _ => Err(format!("unknown variant `{}`", name)),
+        }
+    }
+}